@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use easypassword::core::{derive_entropy, Argon2Costs, ARGON2_M_COST, ARGON2_T_COST};
+
+/// Memory costs to try, in KiB (19 MiB up to 1 GiB).
+const MEMORY_COSTS: [u32; 7] = [19456, 32768, 47104, 65536, 131072, 262144, 1048576];
+const MAX_ITERATIONS: u32 = 10;
+const SAMPLES: usize = 3;
+
+struct Measurement {
+    costs: Argon2Costs,
+    elapsed: Duration,
+}
+
+pub fn run(target_ms: u64, parallelism: u32) -> anyhow::Result<()> {
+    let target = Duration::from_millis(target_ms);
+    let baseline = Argon2Costs {
+        p_cost: parallelism,
+        ..Default::default()
+    };
+    baseline.validate()?;
+
+    if cfg!(debug_assertions) {
+        println!("warning: this is a debug build; timings are much slower than release builds\n");
+    }
+
+    println!(
+        "Target: {} ms per password, parallelism {}",
+        target_ms, parallelism
+    );
+    println!();
+    println!("{:>10}  {:>4}  {:>10}", "m_cost", "t", "time");
+
+    let mut results = Vec::new();
+    for m_cost in MEMORY_COSTS {
+        if m_cost < 8 * parallelism {
+            continue;
+        }
+        let mut fastest_for_memory = None;
+        for t_cost in 1..=MAX_ITERATIONS {
+            let costs = Argon2Costs {
+                m_cost,
+                t_cost,
+                p_cost: parallelism,
+            };
+            let elapsed = measure(&costs)?;
+            println!(
+                "{:>10}  {:>4}  {:>7} ms",
+                m_cost,
+                t_cost,
+                elapsed.as_millis()
+            );
+            fastest_for_memory.get_or_insert(elapsed);
+            if elapsed > target {
+                break;
+            }
+            results.push(Measurement { costs, elapsed });
+        }
+        // If a single pass at this memory cost misses the target, more memory will too.
+        if fastest_for_memory.is_some_and(|elapsed| elapsed > target) {
+            break;
+        }
+    }
+
+    println!();
+    match recommend(&results) {
+        Some(best) => {
+            println!(
+                "Recommended: m={} t={} p={} ({} ms)",
+                best.costs.m_cost,
+                best.costs.t_cost,
+                best.costs.p_cost,
+                best.elapsed.as_millis()
+            );
+            println!();
+            print_snippet(&best.costs);
+        }
+        None => {
+            println!(
+                "No tested parameters meet {} ms on this machine. Keep the defaults (m={}, t={}) \
+                 or raise the target.",
+                target_ms, ARGON2_M_COST, ARGON2_T_COST
+            );
+        }
+    }
+
+    println!();
+    println!("Note: changing Argon2 costs changes every password derived with them.");
+    Ok(())
+}
+
+/// Median of a few runs, so one slow run does not skew the recommendation.
+fn measure(costs: &Argon2Costs) -> anyhow::Result<Duration> {
+    let salt = [0u8; 32];
+    let mut samples = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        derive_entropy("easypassword-bench", &salt, costs)?;
+        samples.push(start.elapsed());
+    }
+    samples.sort();
+    Ok(samples[SAMPLES / 2])
+}
+
+/// The strongest measurement within the target: highest memory-time product,
+/// preferring more memory on ties. Never weaker than the defaults.
+fn recommend(results: &[Measurement]) -> Option<&Measurement> {
+    let minimum = u64::from(ARGON2_M_COST) * u64::from(ARGON2_T_COST);
+    results
+        .iter()
+        .filter(|m| u64::from(m.costs.m_cost) * u64::from(m.costs.t_cost) >= minimum)
+        .max_by_key(|m| {
+            (
+                u64::from(m.costs.m_cost) * u64::from(m.costs.t_cost),
+                m.costs.m_cost,
+            )
+        })
+}
+
+fn print_snippet(costs: &Argon2Costs) {
+    println!("[default]");
+    println!("argon2_m_cost = {}", costs.m_cost);
+    println!("argon2_t_cost = {}", costs.t_cost);
+    println!("argon2_p_cost = {}", costs.p_cost);
+}
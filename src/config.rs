@@ -1,22 +1,49 @@
-use serde::{Deserialize, Serialize};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
-use crate::core::{GenerationMode, PasswordConfig};
+use crate::core::{
+    AlgorithmVersion, Argon2Costs, GenerationMode, PassphraseConfig, PasswordConfig,
+    UsernameConfig, ARGON2_M_COST, ARGON2_P_COST, ARGON2_T_COST, MIN_PIN_LENGTH,
+};
+use crate::core::{PasswordPolicy, DEFAULT_TEMPLATE};
 use crate::error::{EasyPasswordError, Result};
+use crate::otp::OtpEntry;
+use crate::site::{normalize_site, site_host, SiteInput};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
-    #[serde(default)]
+    /// A file without `[default]` predates versioning, like one whose
+    /// `[default]` omits `version`.
+    #[serde(default = "DefaultConfig::legacy")]
     pub default: DefaultConfig,
     #[serde(default)]
     pub sites: HashMap<String, SiteConfig>,
+    /// Named sets of settings that sites join with `group`, e.g. `[groups.banking]`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, SiteConfig>,
+    /// Sites that share another site's password and settings, e.g.
+    /// `"amazon.de" = "amazon.com"`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub aliases: HashMap<String, String>,
+    /// Encrypted one-time code secrets, by lowercase name.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        deserialize_with = "lowercase_keys"
+    )]
+    pub otp: HashMap<String, OtpEntry>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultConfig {
     pub master_key: Option<String>,
+    /// Fingerprint the master key must have, e.g. `pink coveting-swoosh`.
+    #[serde(default)]
+    pub expected_fingerprint: Option<String>,
     #[serde(default)]
     pub autostart: bool,
     #[serde(default = "default_length")]
@@ -29,12 +56,83 @@ pub struct DefaultConfig {
     pub digits: bool,
     #[serde(default = "default_true")]
     pub symbols: bool,
+    #[serde(default)]
+    pub symbol_set: Option<String>,
+    #[serde(default)]
+    pub exclude: String,
+    #[serde(default)]
+    pub avoid_ambiguous: bool,
+    #[serde(default)]
+    pub alphabet: Option<String>,
+    #[serde(default)]
+    pub min_lowercase: usize,
+    #[serde(default)]
+    pub min_uppercase: usize,
+    #[serde(default)]
+    pub min_digits: usize,
+    #[serde(default)]
+    pub min_symbols: usize,
+    #[serde(default)]
+    pub max_consecutive: Option<usize>,
+    #[serde(default)]
+    pub start_with_letter: bool,
+    #[serde(default)]
+    pub max_length: Option<usize>,
     #[serde(default = "default_trigger_prefix")]
     pub trigger_prefix: String,
     #[serde(default = "default_concat_trigger_prefix")]
     pub concat_trigger_prefix: String,
+    #[serde(default = "default_passphrase_trigger_prefix")]
+    pub passphrase_trigger_prefix: String,
+    #[serde(default = "default_username_trigger_prefix")]
+    pub username_trigger_prefix: String,
+    /// Labels of the SSH keys the built-in agent serves; empty disables it.
+    #[serde(default)]
+    pub ssh_agent_keys: Vec<String>,
+    /// Agent socket path; defaults to `agent.sock` next to the config file.
+    #[serde(default)]
+    pub ssh_agent_socket: Option<String>,
+    /// Reduce URLs and subdomains to the registrable domain. Configs written
+    /// before normalization existed omit this and keep the site as typed.
+    #[serde(default)]
+    pub normalize_sites: bool,
+    /// Read `alice@github.com` as the account `alice` on `github.com`. Configs
+    /// written before accounts existed omit this and keep `@` in the site name.
+    #[serde(default)]
+    pub account_syntax: bool,
     #[serde(default)]
     pub mode: GenerationMode,
+    /// Configs written before versioning existed omit this and stay on version 1.
+    #[serde(default = "legacy_version")]
+    pub version: AlgorithmVersion,
+    #[serde(default = "default_argon2_m_cost")]
+    pub argon2_m_cost: u32,
+    #[serde(default = "default_argon2_t_cost")]
+    pub argon2_t_cost: u32,
+    #[serde(default = "default_argon2_p_cost")]
+    pub argon2_p_cost: u32,
+    #[serde(default = "default_passphrase_words")]
+    pub passphrase_words: usize,
+    #[serde(default = "default_passphrase_separator")]
+    pub passphrase_separator: String,
+    #[serde(default)]
+    pub passphrase_capitalize: bool,
+    #[serde(default = "default_pin_length")]
+    pub pin_length: usize,
+    #[serde(default = "default_username_words")]
+    pub username_words: usize,
+    #[serde(default = "default_username_digits")]
+    pub username_digits: usize,
+    #[serde(default = "default_answer_words")]
+    pub answer_words: usize,
+    #[serde(default = "default_template")]
+    pub template: String,
+    #[serde(default)]
+    pub template_pattern: Option<String>,
+    #[serde(default)]
+    pub spectre_full_name: Option<String>,
+    #[serde(default)]
+    pub lesspass_login: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -44,8 +142,55 @@ pub struct SiteConfig {
     pub uppercase: Option<bool>,
     pub digits: Option<bool>,
     pub symbols: Option<bool>,
+    pub symbol_set: Option<String>,
+    pub exclude: Option<String>,
+    pub avoid_ambiguous: Option<bool>,
+    pub alphabet: Option<String>,
+    pub min_lowercase: Option<usize>,
+    pub min_uppercase: Option<usize>,
+    pub min_digits: Option<usize>,
+    pub min_symbols: Option<usize>,
+    pub max_consecutive: Option<usize>,
+    pub start_with_letter: Option<bool>,
+    pub max_length: Option<usize>,
     pub counter: Option<u32>,
     pub mode: Option<GenerationMode>,
+    pub version: Option<AlgorithmVersion>,
+    pub argon2_m_cost: Option<u32>,
+    pub argon2_t_cost: Option<u32>,
+    pub argon2_p_cost: Option<u32>,
+    pub passphrase_words: Option<usize>,
+    pub passphrase_separator: Option<String>,
+    pub passphrase_capitalize: Option<bool>,
+    pub pin_length: Option<usize>,
+    pub username_words: Option<usize>,
+    pub username_digits: Option<usize>,
+    pub answer_words: Option<usize>,
+    pub template: Option<String>,
+    pub template_pattern: Option<String>,
+    pub spectre_full_name: Option<String>,
+    pub lesspass_login: Option<String>,
+    /// Set to `false` to derive from the site name exactly as typed, e.g. to
+    /// keep `gist.github.com` apart from `github.com`.
+    pub normalize: Option<bool>,
+    /// Overrides `account_syntax`; looked up with the site exactly as typed,
+    /// so `[sites."work@github"]` can keep its `@`.
+    pub account_syntax: Option<bool>,
+    /// Group whose settings this site inherits and overrides.
+    pub group: Option<String>,
+    /// Groups only: the group this one inherits and overrides.
+    pub extends: Option<String>,
+    /// Canonical site this one is an alias of; its other settings are ignored.
+    pub alias_of: Option<String>,
+    /// Account used on this site when the trigger does not name one.
+    pub account: Option<String>,
+    /// Per-account overrides by lowercase name, e.g. `[sites.github.accounts.work]`.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        deserialize_with = "lowercase_keys"
+    )]
+    pub accounts: HashMap<String, SiteConfig>,
 }
 
 fn default_length() -> usize {
@@ -60,20 +205,126 @@ fn default_trigger_prefix() -> String {
 fn default_concat_trigger_prefix() -> String {
     "!!".to_string()
 }
+fn default_passphrase_trigger_prefix() -> String {
+    "~~".to_string()
+}
+fn default_username_trigger_prefix() -> String {
+    "@@".to_string()
+}
+/// Lowercases table keys for case-insensitive lookup, e.g. `[otp.GitHub]`,
+/// rejecting keys that differ only in case.
+fn lowercase_keys<'de, D, T>(deserializer: D) -> std::result::Result<HashMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let table = HashMap::<String, T>::deserialize(deserializer)?;
+    let mut lowercased = HashMap::with_capacity(table.len());
+    for (key, value) in table {
+        let key = key.to_lowercase();
+        if lowercased.contains_key(&key) {
+            return Err(serde::de::Error::custom(format!(
+                "duplicate entry {} (names are case-insensitive)",
+                key
+            )));
+        }
+        lowercased.insert(key, value);
+    }
+    Ok(lowercased)
+}
+fn legacy_version() -> AlgorithmVersion {
+    AlgorithmVersion::V1
+}
+fn default_argon2_m_cost() -> u32 {
+    ARGON2_M_COST
+}
+fn default_argon2_t_cost() -> u32 {
+    ARGON2_T_COST
+}
+fn default_argon2_p_cost() -> u32 {
+    ARGON2_P_COST
+}
+fn default_passphrase_words() -> usize {
+    PassphraseConfig::default().words
+}
+fn default_passphrase_separator() -> String {
+    PassphraseConfig::default().separator
+}
+fn default_pin_length() -> usize {
+    MIN_PIN_LENGTH
+}
+fn default_username_words() -> usize {
+    UsernameConfig::default().words
+}
+fn default_username_digits() -> usize {
+    UsernameConfig::default().digits
+}
+fn default_answer_words() -> usize {
+    PasswordConfig::default().answer_words
+}
+fn default_template() -> String {
+    DEFAULT_TEMPLATE.to_string()
+}
 
 impl Default for DefaultConfig {
     fn default() -> Self {
         Self {
             master_key: None,
+            expected_fingerprint: None,
             autostart: false,
             length: default_length(),
             lowercase: true,
             uppercase: true,
             digits: true,
             symbols: true,
+            symbol_set: None,
+            exclude: String::new(),
+            avoid_ambiguous: false,
+            alphabet: None,
+            min_lowercase: 0,
+            min_uppercase: 0,
+            min_digits: 0,
+            min_symbols: 0,
+            max_consecutive: None,
+            start_with_letter: false,
+            max_length: None,
             trigger_prefix: default_trigger_prefix(),
             concat_trigger_prefix: default_concat_trigger_prefix(),
+            passphrase_trigger_prefix: default_passphrase_trigger_prefix(),
+            username_trigger_prefix: default_username_trigger_prefix(),
+            ssh_agent_keys: Vec::new(),
+            ssh_agent_socket: None,
+            normalize_sites: true,
+            account_syntax: true,
             mode: GenerationMode::default(),
+            version: AlgorithmVersion::LATEST,
+            argon2_m_cost: default_argon2_m_cost(),
+            argon2_t_cost: default_argon2_t_cost(),
+            argon2_p_cost: default_argon2_p_cost(),
+            passphrase_words: default_passphrase_words(),
+            passphrase_separator: default_passphrase_separator(),
+            passphrase_capitalize: false,
+            pin_length: default_pin_length(),
+            username_words: default_username_words(),
+            username_digits: default_username_digits(),
+            answer_words: default_answer_words(),
+            template: default_template(),
+            template_pattern: None,
+            spectre_full_name: None,
+            lesspass_login: String::new(),
+        }
+    }
+}
+
+impl DefaultConfig {
+    /// What serde fills in for keys a config file omits: the settings in
+    /// effect before those keys existed, so old files keep their passwords.
+    fn legacy() -> Self {
+        Self {
+            version: legacy_version(),
+            normalize_sites: false,
+            account_syntax: false,
+            ..Self::default()
         }
     }
 }
@@ -84,6 +335,7 @@ impl Config {
         if path.exists() {
             let content = fs::read_to_string(&path)?;
             let config: Config = toml::from_str(&content).map_err(EasyPasswordError::TomlParse)?;
+            config.validate()?;
             Ok(config)
         } else {
             let config = Config::default();
@@ -103,43 +355,979 @@ impl Config {
         Ok(())
     }
 
+    /// Checks settings that cannot be expressed in the TOML schema.
+    pub fn validate(&self) -> Result<()> {
+        self.resolve(&[])
+            .validate()
+            .map_err(|e| EasyPasswordError::Config(format!("[default] {}", e)))?;
+        for (site, site_config) in &self.sites {
+            if let Some(Err(e)) = regex_rule(site) {
+                return Err(EasyPasswordError::Config(format!(
+                    "[sites.\"{}\"] invalid pattern: {}",
+                    site, e
+                )));
+            }
+            let prefix = format!("[sites.{}]", site);
+            let site_layers = self.validate_layer(&prefix, &[], site_config)?;
+            for (account, account_config) in &site_config.accounts {
+                let prefix = format!("[sites.{}.accounts.{}]", site, account);
+                if !account_config.accounts.is_empty() {
+                    return Err(EasyPasswordError::Config(format!(
+                        "{} accounts cannot be nested",
+                        prefix
+                    )));
+                }
+                self.validate_layer(&prefix, &site_layers, account_config)?;
+            }
+        }
+        for (name, group) in &self.groups {
+            let prefix = format!("[groups.{}]", name);
+            if group.group.is_some() {
+                return Err(EasyPasswordError::Config(format!(
+                    "{} groups inherit with `extends`, not `group`",
+                    prefix
+                )));
+            }
+            let layers = self
+                .group_layers(Some(name))
+                .map_err(|e| EasyPasswordError::Config(format!("{} {}", prefix, e)))?;
+            let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+            self.resolve(&layers)
+                .validate()
+                .map_err(|e| EasyPasswordError::Config(format!("{} {}", prefix, e)))?;
+        }
+        for alias in self.aliases.keys().chain(
+            self.sites
+                .iter()
+                .filter(|(_, s)| s.alias_of.is_some())
+                .map(|(k, _)| k),
+        ) {
+            self.follow_aliases(alias)?;
+        }
+        for (name, entry) in &self.otp {
+            entry
+                .validate()
+                .map_err(|e| EasyPasswordError::Config(format!("[otp.{}] {}", name, e)))?;
+        }
+        Ok(())
+    }
+
+    /// Checks a site or account table on top of `parents`, returning the layers
+    /// it resolves with.
+    fn validate_layer<'a>(
+        &'a self,
+        prefix: &str,
+        parents: &[&'a SiteConfig],
+        layer: &'a SiteConfig,
+    ) -> Result<Vec<&'a SiteConfig>> {
+        if layer.extends.is_some() {
+            return Err(EasyPasswordError::Config(format!(
+                "{} sites join a group with `group`, not `extends`",
+                prefix
+            )));
+        }
+        let groups = self
+            .group_layers(layer.group.as_deref())
+            .map_err(|e| EasyPasswordError::Config(format!("{} {}", prefix, e)))?;
+        let mut layers = parents.to_vec();
+        layers.extend(groups.into_iter().map(|(_, s)| s));
+        layers.push(layer);
+        self.resolve(&layers)
+            .validate()
+            .map_err(|e| EasyPasswordError::Config(format!("{} {}", prefix, e)))?;
+        Ok(layers)
+    }
+
+    /// Checks a master key's fingerprint against `expected_fingerprint`, if set.
+    pub fn check_fingerprint(&self, fingerprint: &str) -> Result<()> {
+        match &self.default.expected_fingerprint {
+            Some(expected) if !expected.trim().eq_ignore_ascii_case(fingerprint) => {
+                Err(EasyPasswordError::FingerprintMismatch {
+                    expected: expected.trim().to_string(),
+                    actual: fingerprint.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn otp_entry(&self, name: &str) -> Option<&OtpEntry> {
+        self.otp.get(&name.to_lowercase())
+    }
+
+    /// Moves an HOTP entry's counter past the code just used, returning the new value.
+    pub fn advance_hotp_counter(&mut self, name: &str) -> Option<u64> {
+        let entry = self.otp.get_mut(&name.to_lowercase())?;
+        entry.counter = entry.counter.saturating_add(1);
+        Some(entry.counter)
+    }
+
     pub fn config_path() -> Result<PathBuf> {
         let config_dir = dirs::config_dir()
             .ok_or_else(|| EasyPasswordError::Config("Cannot find config directory".to_string()))?;
         Ok(config_dir.join("easypassword").join("config.toml"))
     }
 
+    pub fn ssh_agent_socket_path(&self) -> Result<PathBuf> {
+        match &self.default.ssh_agent_socket {
+            Some(path) => Ok(PathBuf::from(path)),
+            None => Ok(Self::config_path()?.with_file_name("agent.sock")),
+        }
+    }
+
     pub fn get_password_config(&self, site: &str) -> PasswordConfig {
-        let site_lower = site.to_lowercase();
-        let site_config = self.sites.get(&site_lower);
+        self.get_account_password_config(site, None)
+    }
+
+    /// Settings for an account on a site. Without an explicit account, the
+    /// site's `account` is used, if set.
+    pub fn get_account_password_config(&self, site: &str, account: Option<&str>) -> PasswordConfig {
+        let (layers, account) = self.layers(site, account);
+        if !layers.is_empty() {
+            log::info!(
+                "[CONFIG] site={} matched {}",
+                site,
+                layer_names(&layers).join(" < ")
+            );
+        }
+        let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+        let mut config = self.resolve(&layers);
+        if let Some(normalize) = self.rule_normalize(&site_host(&self.canonical_site(site))) {
+            config.normalize_site = pick(&layers, |s| s.normalize).unwrap_or(normalize);
+        }
+        config.account = account;
+        config
+    }
+
+    /// Splits typed input like [`SiteInput::parse`] where `account_syntax` is
+    /// on for the site as typed; elsewhere `@` stays part of the site name, as
+    /// it was before accounts existed.
+    pub fn parse_site<'a>(&self, input: &'a str) -> SiteInput<'a> {
+        let unsplit = SiteInput::parse_without_account(input);
+        if unsplit.otp || !self.account_syntax(unsplit.site) {
+            return unsplit;
+        }
+        SiteInput::parse(input)
+    }
+
+    fn account_syntax(&self, site: &str) -> bool {
+        let (layers, _) = self.layers(site, None);
+        let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+        pick(&layers, |s| s.account_syntax).unwrap_or(self.default.account_syntax)
+    }
+
+    /// The group a site's settings inherit from, as named by its most
+    /// specific table.
+    pub fn site_group(&self, site: &str, account: Option<&str>) -> Option<String> {
+        let (layers, _) = self.layers(site, account);
+        let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+        pick(&layers, |s| s.group.clone())
+    }
+
+    /// The tables a site's settings come from, least specific first, e.g.
+    /// `[groups.banking]` then `[sites."chase.com"]`.
+    pub fn matched_tables(&self, site: &str, account: Option<&str>) -> Vec<String> {
+        layer_names(&self.layers(site, account).0)
+    }
+
+    /// The tables that apply to an account on a site, least specific first,
+    /// named by their TOML path, along with the account in effect. Each site
+    /// rule comes after the groups it inherits from: the matching `/regex/`
+    /// rule, the longest matching glob rule, the entry for the site itself,
+    /// then the entry for the account.
+    fn layers(
+        &self,
+        site: &str,
+        account: Option<&str>,
+    ) -> (Vec<(String, &SiteConfig)>, Option<String>) {
+        let (key, patterns) = self.match_site(site);
+        let mut layers = Vec::new();
+        for (rule, site_config) in patterns
+            .into_iter()
+            .chain(self.sites.get_key_value(&key).map(|(k, s)| (k.as_str(), s)))
+        {
+            self.push_layer(&mut layers, format!("sites.\"{}\"", rule), site_config);
+        }
+
+        let configs: Vec<&SiteConfig> = layers.iter().map(|(_, s)| *s).collect();
+        let account = account
+            .map(str::to_string)
+            .or_else(|| pick(&configs, |s| s.account.clone()));
+        let account_config = account.as_ref().and_then(|account| {
+            layers.iter().rev().find_map(|(name, s)| {
+                s.accounts
+                    .get_key_value(&account.to_lowercase())
+                    .map(|(a, s)| (format!("{}.accounts.{}", name, a), s))
+            })
+        });
+        if let Some((name, account_config)) = account_config {
+            self.push_layer(&mut layers, name, account_config);
+        }
+        (layers, account)
+    }
 
+    /// Adds a table after the groups it inherits from.
+    fn push_layer<'a>(
+        &'a self,
+        layers: &mut Vec<(String, &'a SiteConfig)>,
+        name: String,
+        layer: &'a SiteConfig,
+    ) {
+        // `validate` rejects unknown groups and cycles when the config is loaded.
+        layers.extend(
+            self.group_layers(layer.group.as_deref())
+                .unwrap_or_default(),
+        );
+        layers.push((name, layer));
+    }
+
+    /// A group and the groups it extends, least specific first.
+    fn group_layers(&self, group: Option<&str>) -> Result<Vec<(String, &SiteConfig)>> {
+        let mut chain: Vec<(String, &SiteConfig)> = Vec::new();
+        let mut next = group;
+        while let Some(name) = next {
+            let path = format!("groups.{}", name);
+            if chain.iter().any(|(p, _)| *p == path) {
+                let names: Vec<&str> = chain.iter().map(|(p, _)| &p["groups.".len()..]).collect();
+                return Err(EasyPasswordError::Config(format!(
+                    "group cycle: {} -> {}",
+                    names.join(" -> "),
+                    name
+                )));
+            }
+            let group = self
+                .groups
+                .get(name)
+                .ok_or_else(|| EasyPasswordError::Config(format!("unknown group {}", name)))?;
+            chain.push((path, group));
+            next = group.extends.as_deref();
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+
+    /// The key of the site's own entry, and the pattern rules matching its
+    /// host. Patterns see the full host, before normalization, so
+    /// `*.corp.example.com` can tell internal hosts apart.
+    fn match_site(&self, site: &str) -> (String, Vec<(&str, &SiteConfig)>) {
+        let site = self.canonical_site(site);
+        let host = site_host(&site);
+        let normalize = self
+            .rule_normalize(&host)
+            .unwrap_or(self.default.normalize_sites);
+        (self.site_key(&site, normalize), self.pattern_rules(&host))
+    }
+
+    /// The matching `/regex/` rule, then the longest matching glob rule.
+    fn pattern_rules(&self, host: &str) -> Vec<(&str, &SiteConfig)> {
+        // Several matching regexes cannot be ranked; the first by key wins.
+        let regex = self
+            .sites
+            .iter()
+            .filter(|(rule, _)| {
+                regex_rule(rule).is_some_and(|re| re.is_ok_and(|re| re.is_match(host)))
+            })
+            .min_by_key(|(rule, _)| *rule);
+        let glob = self
+            .sites
+            .iter()
+            .filter(|(rule, _)| is_glob_rule(rule) && glob_matches(rule, host))
+            .max_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(b.cmp(a)));
+        regex
+            .into_iter()
+            .chain(glob)
+            .map(|(rule, s)| (rule.as_str(), s))
+            .collect()
+    }
+
+    /// `normalize` for a host that pattern rules match, from the rules and
+    /// their groups. Rules that leave it unset keep each host apart rather
+    /// than reduce them all to one registrable domain. `None` if no rule
+    /// matches.
+    fn rule_normalize(&self, host: &str) -> Option<bool> {
+        let rules = self.pattern_rules(host);
+        if rules.is_empty() {
+            return None;
+        }
+        let mut layers = Vec::new();
+        for (rule, site_config) in rules {
+            self.push_layer(&mut layers, rule.to_string(), site_config);
+        }
+        let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+        Some(pick(&layers, |s| s.normalize).unwrap_or(false))
+    }
+
+    /// The site passwords are derived from: the alias target if `site` is an
+    /// alias, the exact host if pattern rules keep it apart, otherwise `site`
+    /// unchanged.
+    pub fn canonical_site<'a>(&self, site: &'a str) -> Cow<'a, str> {
+        let site = match self.follow_aliases(site) {
+            Ok(Some(canonical)) => Cow::Owned(canonical),
+            // `validate` rejects alias cycles when the config is loaded.
+            _ => Cow::Borrowed(site),
+        };
+        let host = site_host(&site);
+        if self.rule_normalize(&host) == Some(false) {
+            return Cow::Owned(host);
+        }
+        site
+    }
+
+    /// Follows `[aliases]` and `alias_of` from a site as typed, returning the
+    /// canonical site's key, or `None` when the site is not an alias.
+    fn follow_aliases(&self, site: &str) -> Result<Option<String>> {
+        let mut key = self.alias_key(site);
+        let mut chain = vec![key.clone()];
+        while let Some(target) = self
+            .aliases
+            .get(&key)
+            .or_else(|| self.sites.get(&key).and_then(|s| s.alias_of.as_ref()))
+        {
+            key = self.alias_key(target);
+            if chain.contains(&key) {
+                return Err(EasyPasswordError::Config(format!(
+                    "alias cycle: {} -> {}",
+                    chain.join(" -> "),
+                    key
+                )));
+            }
+            chain.push(key.clone());
+        }
+        Ok((chain.len() > 1).then_some(key))
+    }
+
+    /// The key a site is looked up by in `[aliases]`, normalized like in
+    /// [`Self::match_site`].
+    fn alias_key(&self, site: &str) -> String {
+        let normalize = self
+            .rule_normalize(&site_host(site))
+            .unwrap_or(self.default.normalize_sites);
+        self.site_key(site, normalize)
+    }
+
+    /// The `[sites]` key for a site as typed. An entry or alias named exactly
+    /// like the input wins; otherwise the input is normalized if `normalize`.
+    fn site_key(&self, site: &str, normalize: bool) -> String {
+        let site = site.to_lowercase();
+        if !normalize || self.sites.contains_key(&site) || self.aliases.contains_key(&site) {
+            return site;
+        }
+        normalize_site(&site)
+    }
+
+    /// Applies overrides on top of `[default]`; later layers win.
+    fn resolve(&self, layers: &[&SiteConfig]) -> PasswordConfig {
         PasswordConfig {
-            length: site_config
-                .and_then(|s| s.length)
-                .unwrap_or(self.default.length),
-            use_lowercase: site_config
-                .and_then(|s| s.lowercase)
-                .unwrap_or(self.default.lowercase),
-            use_uppercase: site_config
-                .and_then(|s| s.uppercase)
-                .unwrap_or(self.default.uppercase),
-            use_digits: site_config
-                .and_then(|s| s.digits)
-                .unwrap_or(self.default.digits),
-            use_symbols: site_config
-                .and_then(|s| s.symbols)
-                .unwrap_or(self.default.symbols),
-            mode: site_config
-                .and_then(|s| s.mode)
-                .unwrap_or(self.default.mode),
+            length: pick(layers, |s| s.length).unwrap_or(self.default.length),
+            use_lowercase: pick(layers, |s| s.lowercase).unwrap_or(self.default.lowercase),
+            use_uppercase: pick(layers, |s| s.uppercase).unwrap_or(self.default.uppercase),
+            use_digits: pick(layers, |s| s.digits).unwrap_or(self.default.digits),
+            use_symbols: pick(layers, |s| s.symbols).unwrap_or(self.default.symbols),
+            symbol_set: pick(layers, |s| s.symbol_set.clone())
+                .or_else(|| self.default.symbol_set.clone()),
+            exclude: pick(layers, |s| s.exclude.clone())
+                .unwrap_or_else(|| self.default.exclude.clone()),
+            avoid_ambiguous: pick(layers, |s| s.avoid_ambiguous)
+                .unwrap_or(self.default.avoid_ambiguous),
+            alphabet: pick(layers, |s| s.alphabet.clone())
+                .or_else(|| self.default.alphabet.clone()),
+            policy: PasswordPolicy {
+                min_lowercase: pick(layers, |s| s.min_lowercase)
+                    .unwrap_or(self.default.min_lowercase),
+                min_uppercase: pick(layers, |s| s.min_uppercase)
+                    .unwrap_or(self.default.min_uppercase),
+                min_digits: pick(layers, |s| s.min_digits).unwrap_or(self.default.min_digits),
+                min_symbols: pick(layers, |s| s.min_symbols).unwrap_or(self.default.min_symbols),
+                max_consecutive: pick(layers, |s| s.max_consecutive)
+                    .or(self.default.max_consecutive),
+                start_with_letter: pick(layers, |s| s.start_with_letter)
+                    .unwrap_or(self.default.start_with_letter),
+                max_length: pick(layers, |s| s.max_length).or(self.default.max_length),
+            },
+            mode: pick(layers, |s| s.mode).unwrap_or(self.default.mode),
+            version: pick(layers, |s| s.version).unwrap_or(self.default.version),
+            argon2: Argon2Costs {
+                m_cost: pick(layers, |s| s.argon2_m_cost).unwrap_or(self.default.argon2_m_cost),
+                t_cost: pick(layers, |s| s.argon2_t_cost).unwrap_or(self.default.argon2_t_cost),
+                p_cost: pick(layers, |s| s.argon2_p_cost).unwrap_or(self.default.argon2_p_cost),
+            },
+            passphrase: PassphraseConfig {
+                words: pick(layers, |s| s.passphrase_words)
+                    .unwrap_or(self.default.passphrase_words),
+                separator: pick(layers, |s| s.passphrase_separator.clone())
+                    .unwrap_or_else(|| self.default.passphrase_separator.clone()),
+                capitalize: pick(layers, |s| s.passphrase_capitalize)
+                    .unwrap_or(self.default.passphrase_capitalize),
+            },
+            pin_length: pick(layers, |s| s.pin_length).unwrap_or(self.default.pin_length),
+            username: UsernameConfig {
+                words: pick(layers, |s| s.username_words).unwrap_or(self.default.username_words),
+                digits: pick(layers, |s| s.username_digits).unwrap_or(self.default.username_digits),
+            },
+            answer_words: pick(layers, |s| s.answer_words).unwrap_or(self.default.answer_words),
+            template: pick(layers, |s| s.template.clone())
+                .unwrap_or_else(|| self.default.template.clone()),
+            // A layer naming a template opts out of an inherited custom pattern.
+            template_pattern: layers
+                .iter()
+                .rev()
+                .find(|s| s.template.is_some() || s.template_pattern.is_some())
+                .map_or_else(
+                    || self.default.template_pattern.clone(),
+                    |s| s.template_pattern.clone(),
+                ),
+            spectre_full_name: pick(layers, |s| s.spectre_full_name.clone())
+                .or_else(|| self.default.spectre_full_name.clone()),
+            lesspass_login: pick(layers, |s| s.lesspass_login.clone())
+                .unwrap_or_else(|| self.default.lesspass_login.clone()),
+            account: None,
+            normalize_site: pick(layers, |s| s.normalize).unwrap_or(self.default.normalize_sites),
         }
     }
 
     pub fn get_counter(&self, site: &str) -> u32 {
-        let site_lower = site.to_lowercase();
-        self.sites
-            .get(&site_lower)
-            .and_then(|s| s.counter)
-            .unwrap_or(1)
+        self.get_account_counter(site, None)
+    }
+
+    pub fn get_account_counter(&self, site: &str, account: Option<&str>) -> u32 {
+        let (layers, _) = self.layers(site, account);
+        let layers: Vec<&SiteConfig> = layers.into_iter().map(|(_, s)| s).collect();
+        pick(&layers, |s| s.counter).unwrap_or(1)
+    }
+
+    /// Increments the site's counter, creating a site entry if needed, and returns the new value.
+    pub fn bump_counter(&mut self, site: &str) -> u32 {
+        self.bump_account_counter(site, None)
+    }
+
+    /// Increments the counter of the account in effect on the site, or the
+    /// site's own counter when there is none.
+    pub fn bump_account_counter(&mut self, site: &str, account: Option<&str>) -> u32 {
+        let counter = self.get_account_counter(site, account).saturating_add(1);
+        let (_, account) = self.layers(site, account);
+        let (site_key, _) = self.match_site(site);
+        let site_config = self.sites.entry(site_key).or_default();
+        match account {
+            Some(account) => {
+                site_config
+                    .accounts
+                    .entry(account.to_lowercase())
+                    .or_default()
+                    .counter = Some(counter)
+            }
+            None => site_config.counter = Some(counter),
+        }
+        counter
+    }
+}
+
+/// A `/regex/` site rule's compiled pattern, or `None` for other keys.
+fn regex_rule(key: &str) -> Option<std::result::Result<Regex, regex::Error>> {
+    let pattern = key.strip_prefix('/')?.strip_suffix('/')?;
+    Some(Regex::new(pattern))
+}
+
+fn is_glob_rule(key: &str) -> bool {
+    key.contains('*') && regex_rule(key).is_none()
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+fn layer_names(layers: &[(String, &SiteConfig)]) -> Vec<String> {
+    layers
+        .iter()
+        .map(|(name, _)| format!("[{}]", name))
+        .collect()
+}
+
+/// The value from the most specific layer that sets it.
+fn pick<T>(layers: &[&SiteConfig], field: impl Fn(&SiteConfig) -> Option<T>) -> Option<T> {
+    layers.iter().rev().find_map(|layer| field(layer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_version_is_legacy() {
+        let config: Config = toml::from_str("[default]\nlength = 20\n").unwrap();
+        assert_eq!(config.default.version, AlgorithmVersion::V1);
+        assert_eq!(Config::default().default.version, AlgorithmVersion::LATEST);
+    }
+
+    #[test]
+    fn test_missing_default_table_is_legacy() {
+        for content in ["", "[sites.x]\nlength = 20\n"] {
+            let config: Config = toml::from_str(content).unwrap();
+            assert_eq!(config.default.version, AlgorithmVersion::V1);
+            assert!(!config.default.normalize_sites);
+            let site = config.get_password_config("x");
+            assert_eq!(site.version, AlgorithmVersion::V1);
+            assert!(!site.normalize_site);
+        }
+    }
+
+    #[test]
+    fn test_site_version_override() {
+        let config: Config =
+            toml::from_str("[default]\nversion = 1\n\n[sites.github]\nversion = 1\n").unwrap();
+        assert_eq!(
+            config.get_password_config("github").version,
+            AlgorithmVersion::V1
+        );
+        assert!(toml::from_str::<Config>("[default]\nversion = 99\n").is_err());
+    }
+
+    #[test]
+    fn test_argon2_costs_per_site() {
+        let config: Config =
+            toml::from_str("[default]\nargon2_t_cost = 3\n\n[sites.bank]\nargon2_m_cost = 65536\n")
+                .unwrap();
+        assert!(config.validate().is_ok());
+
+        let bank = config.get_password_config("bank").argon2;
+        assert_eq!((bank.m_cost, bank.t_cost, bank.p_cost), (65536, 3, 1));
+        let other = config.get_password_config("other").argon2;
+        assert_eq!(other.m_cost, ARGON2_M_COST);
+
+        let invalid: Config = toml::from_str("[sites.bank]\nargon2_t_cost = 0\n").unwrap();
+        let err = invalid.validate().unwrap_err().to_string();
+        assert!(err.contains("sites.bank"), "{}", err);
+    }
+
+    #[test]
+    fn test_charset_settings_per_site() {
+        let config: Config = toml::from_str(
+            "[default]\navoid_ambiguous = true\n\n[sites.bank]\nsymbol_set = \"!@#\"\nexclude = \"xyz\"\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let bank = config.get_password_config("bank");
+        assert_eq!(bank.symbol_set.as_deref(), Some("!@#"));
+        assert_eq!(bank.exclude, "xyz");
+        assert!(bank.avoid_ambiguous);
+        assert_eq!(config.get_password_config("other").symbol_set, None);
+
+        let invalid: Config =
+            toml::from_str("[sites.bank]\ndigits = true\nexclude = \"0123456789\"\n").unwrap();
+        let err = invalid.validate().unwrap_err().to_string();
+        assert!(err.contains("sites.bank"), "{}", err);
+    }
+
+    #[test]
+    fn test_policy_per_site() {
+        let config: Config = toml::from_str(
+            "[default]\nversion = 2\n\n[sites.corp]\nmin_digits = 2\nmax_consecutive = 2\nstart_with_letter = true\nmax_length = 12\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let corp = config.get_password_config("corp").policy;
+        assert_eq!(corp.min_digits, 2);
+        assert_eq!(corp.max_consecutive, Some(2));
+        assert!(corp.start_with_letter);
+        assert_eq!(corp.max_length, Some(12));
+        assert!(config.get_password_config("other").policy.is_empty());
+
+        let legacy: Config = toml::from_str("[default]\n\n[sites.corp]\nmin_digits = 2\n").unwrap();
+        let err = legacy.validate().unwrap_err().to_string();
+        assert!(err.contains("version 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_template_per_site() {
+        let config: Config = toml::from_str(
+            "[default]\ntemplate_pattern = \"cvc-cvc\"\n\n[sites.tv]\nmode = \"template\"\ntemplate = \"short\"\n\n[sites.phone]\nmode = \"template\"\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let tv = config.get_password_config("tv");
+        assert_eq!(tv.mode, GenerationMode::Template);
+        assert_eq!(tv.template, "short");
+        assert_eq!(tv.template_pattern, None);
+        let phone = config.get_password_config("phone");
+        assert_eq!(phone.template_pattern.as_deref(), Some("cvc-cvc"));
+
+        let invalid: Config =
+            toml::from_str("[sites.tv]\nmode = \"template\"\ntemplate = \"huge\"\n").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_lesspass_site() {
+        let config: Config = toml::from_str(
+            "[sites.\"example.org\"]\nmode = \"lesspass\"\nlesspass_login = \"contact@example.org\"\nsymbols = false\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let site = config.get_password_config("example.org");
+        assert_eq!(site.mode, GenerationMode::LessPass);
+        assert_eq!(site.lesspass_login, "contact@example.org");
+        assert!(!site.use_symbols);
+    }
+
+    #[test]
+    fn test_accounts_per_site() {
+        let config: Config = toml::from_str(
+            "[sites.github]\nlength = 20\ncounter = 2\n\n[sites.github.accounts.work]\ncounter = 5\nsymbols = false\n\n[sites.gitlab]\naccount = \"alice\"\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let work = config.get_account_password_config("github", Some("Work"));
+        assert_eq!(work.account.as_deref(), Some("Work"));
+        assert_eq!(work.length, 20);
+        assert!(!work.use_symbols);
+        assert_eq!(config.get_account_counter("github", Some("work")), 5);
+
+        let personal = config.get_account_password_config("github", Some("personal"));
+        assert!(personal.use_symbols);
+        assert_eq!(config.get_account_counter("github", Some("personal")), 2);
+        assert_eq!(config.get_password_config("github").account, None);
+
+        let gitlab = config.get_password_config("gitlab");
+        assert_eq!(gitlab.account.as_deref(), Some("alice"));
+
+        let mixed: Config =
+            toml::from_str("[sites.github.accounts.Work]\nsymbols = false\n").unwrap();
+        assert!(
+            !mixed
+                .get_account_password_config("github", Some("work"))
+                .use_symbols
+        );
+        assert!(toml::from_str::<Config>(
+            "[sites.github.accounts.Work]\ncounter = 1\n\n[sites.github.accounts.work]\ncounter = 2\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_check_fingerprint() {
+        let mut config = Config::default();
+        assert!(config.check_fingerprint("pink coveting-swoosh").is_ok());
+
+        config.default.expected_fingerprint = Some(" Pink coveting-swoosh ".to_string());
+        assert!(config.check_fingerprint("pink coveting-swoosh").is_ok());
+        assert!(matches!(
+            config.check_fingerprint("teal lamp-river"),
+            Err(EasyPasswordError::FingerprintMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_otp_entries() {
+        let mut config: Config = toml::from_str(
+            "[otp.github]\nsecret = \"v1:AAAA\"\n\n[otp.bank]\nsecret = \"v1:AAAA\"\nkind = \"hotp\"\ndigits = 8\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.otp_entry("GITHUB").unwrap().digits, 6);
+        assert_eq!(config.advance_hotp_counter("bank"), Some(1));
+        assert_eq!(config.otp_entry("bank").unwrap().counter, 1);
+        assert_eq!(config.advance_hotp_counter("missing"), None);
+
+        let plaintext: Config = toml::from_str("[otp.x]\nsecret = \"JBSWY3DP\"\n").unwrap();
+        assert!(plaintext.validate().is_err());
+
+        let mixed: Config = toml::from_str("[otp.GitHub]\nsecret = \"v1:AAAA\"\n").unwrap();
+        assert!(mixed.otp_entry("github").is_some());
+        assert!(toml::from_str::<Config>(
+            "[otp.GitHub]\nsecret = \"v1:AAAA\"\n\n[otp.github]\nsecret = \"v1:AAAA\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_bump_account_counter() {
+        let mut config = Config::default();
+        assert_eq!(config.bump_account_counter("github", Some("work")), 2);
+        assert_eq!(config.get_account_counter("github", Some("work")), 2);
+        assert_eq!(config.get_counter("github"), 1);
+        assert_eq!(config.bump_counter("github"), 2);
+
+        let nested: Config =
+            toml::from_str("[sites.github.accounts.work.accounts.x]\ncounter = 1\n").unwrap();
+        assert!(nested.validate().is_err());
+    }
+
+    #[test]
+    fn test_site_normalization() {
+        let legacy: Config =
+            toml::from_str("[default]\n\n[sites.\"github.com\"]\nlength = 20\n").unwrap();
+        assert!(!legacy.default.normalize_sites);
+        assert_eq!(legacy.get_password_config("www.github.com").length, 16);
+        assert!(!legacy.get_password_config("github.com").normalize_site);
+
+        let mut config: Config = toml::from_str(
+            "[default]\nnormalize_sites = true\n\n[sites.\"github.com\"]\nlength = 20\ncounter = 3\n\n[sites.\"gist.github.com\"]\nnormalize = false\n",
+        )
+        .unwrap();
+        let github = config.get_password_config("https://www.GitHub.com/login");
+        assert_eq!(github.length, 20);
+        assert!(github.normalize_site);
+        assert_eq!(config.get_counter("api.github.com"), 3);
+        assert_eq!(config.bump_counter("https://github.com/"), 4);
+        assert_eq!(config.get_counter("github.com"), 4);
+
+        let gist = config.get_password_config("gist.github.com");
+        assert_eq!(gist.length, 16);
+        assert!(!gist.normalize_site);
+        assert_eq!(gist.site_name("gist.github.com"), "gist.github.com");
+        assert!(Config::default().default.normalize_sites);
+    }
+
+    #[test]
+    fn test_site_aliases() {
+        let mut config: Config = toml::from_str(
+            "[default]\nnormalize_sites = true\n\n[sites.\"github.com\"]\nlength = 20\n\n[sites.\"sso.corp.example\"]\nalias_of = \"github.com\"\n\n[aliases]\n\"amazon.de\" = \"amazon.com\"\n\"amazon.co.uk\" = \"https://www.amazon.de/\"\n",
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config.canonical_site("sso.corp.example"), "github.com");
+        assert_eq!(
+            config.canonical_site("https://gist.github.com"),
+            "https://gist.github.com"
+        );
+        assert_eq!(config.canonical_site("www.amazon.co.uk"), "amazon.com");
+        assert_eq!(config.canonical_site("example.org"), "example.org");
+        assert_eq!(config.get_password_config("sso.corp.example").length, 20);
+
+        assert_eq!(config.bump_counter("amazon.de"), 2);
+        assert_eq!(config.get_counter("amazon.com"), 2);
+        assert!(!config.sites.contains_key("amazon.de"));
+
+        config
+            .aliases
+            .insert("amazon.com".to_string(), "amazon.co.uk".to_string());
+        assert!(matches!(
+            config.validate(),
+            Err(EasyPasswordError::Config(e)) if e.contains("alias cycle")
+        ));
+        assert_eq!(config.canonical_site("amazon.de"), "amazon.de");
+    }
+
+    #[test]
+    fn test_pattern_rules() {
+        let config: Config = toml::from_str(
+            r#"
+[sites."*.example.com"]
+length = 12
+symbols = false
+
+[sites."*.corp.example.com"]
+length = 32
+
+[sites.'/^db-\d+\./']
+length = 20
+digits = false
+counter = 4
+
+[sites."db-1.corp.example.com"]
+counter = 7
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let exact = config.get_password_config("db-1.corp.example.com");
+        assert_eq!(exact.length, 32);
+        assert!(exact.use_symbols);
+        assert!(!exact.use_digits);
+        assert_eq!(config.get_counter("db-1.corp.example.com"), 7);
+
+        let regex = config.get_password_config("db-2.corp.example.com");
+        assert_eq!(regex.length, 32);
+        assert!(!regex.use_digits);
+        assert_eq!(config.get_counter("db-2.corp.example.com"), 4);
+
+        let glob = config.get_password_config("https://wiki.example.com/page");
+        assert_eq!(glob.length, 12);
+        assert!(!glob.use_symbols);
+        assert!(glob.use_digits);
+        assert_eq!(config.get_password_config("example.com").length, 16);
+        assert_eq!(config.get_password_config("db-3.other.org").length, 20);
+
+        let invalid: Config = toml::from_str("[sites.\"/db-(/\"]\nlength = 20\n").unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_pattern_rules_keep_hosts_apart() {
+        let mut config: Config = toml::from_str(
+            r#"
+[default]
+normalize_sites = true
+
+[sites."*.corp.example.com"]
+length = 32
+
+[sites."*.shop.example.com"]
+normalize = true
+
+[sites."db.corp.example.com"]
+counter = 3
+
+[aliases]
+"example.com" = "example.org"
+"#,
+        )
+        .unwrap();
+
+        let db = config.canonical_site("https://db.corp.example.com/login");
+        assert_eq!(db, "db.corp.example.com");
+        assert!(!config.get_password_config(&db).normalize_site);
+        assert_eq!(config.get_counter(&db), 3);
+        assert_eq!(config.bump_counter("wiki.corp.example.com"), 2);
+        assert_eq!(config.get_counter("wiki.corp.example.com"), 2);
+        assert_eq!(config.get_counter(&db), 3);
+        assert!(!config.sites.contains_key("example.com"));
+
+        // A rule asking for normalization follows the registrable domain's alias.
+        assert_eq!(config.canonical_site("eu.shop.example.com"), "example.org");
+        assert_eq!(config.canonical_site("www.example.com"), "example.org");
+    }
+
+    #[test]
+    fn test_groups() {
+        let config: Config = toml::from_str(
+            r#"
+[default]
+version = 2
+
+[groups.strict]
+min_digits = 2
+avoid_ambiguous = true
+
+[groups.banking]
+extends = "strict"
+length = 24
+symbols = false
+
+[sites.chase]
+group = "banking"
+counter = 3
+
+[sites.chase.accounts.business]
+group = "strict"
+length = 30
+
+[sites."*.bank.example"]
+group = "banking"
+"#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let chase = config.get_password_config("chase");
+        assert_eq!(chase.length, 24);
+        assert!(!chase.use_symbols);
+        assert!(chase.avoid_ambiguous);
+        assert_eq!(chase.policy.min_digits, 2);
+        assert_eq!(config.get_counter("chase"), 3);
+        assert_eq!(
+            config.matched_tables("chase", None),
+            ["[groups.strict]", "[groups.banking]", "[sites.\"chase\"]"]
+        );
+
+        let business = config.get_account_password_config("chase", Some("business"));
+        assert_eq!(business.length, 30);
+        assert!(!business.use_symbols);
+        assert_eq!(
+            config
+                .matched_tables("chase", Some("business"))
+                .last()
+                .unwrap(),
+            "[sites.\"chase\".accounts.business]"
+        );
+        assert_eq!(config.get_password_config("online.bank.example").length, 24);
+
+        let cycle: Config = toml::from_str(
+            "[groups.a]\nextends = \"b\"\n\n[groups.b]\nextends = \"a\"\n\n[sites.x]\ngroup = \"a\"\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            cycle.validate(),
+            Err(EasyPasswordError::Config(e)) if e.contains("group cycle")
+        ));
+        assert_eq!(cycle.get_password_config("x").length, 16);
+
+        let unknown: Config = toml::from_str("[sites.x]\ngroup = \"missing\"\n").unwrap();
+        assert!(unknown.validate().is_err());
+        let misplaced: Config =
+            toml::from_str("[sites.x]\nextends = \"a\"\n\n[groups.a]\n").unwrap();
+        assert!(misplaced.validate().is_err());
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("*.corp.example.com", "db.corp.example.com"));
+        assert!(glob_matches("*.corp.example.com", "a.b.corp.example.com"));
+        assert!(!glob_matches("*.corp.example.com", "corp.example.com"));
+        assert!(glob_matches("build-*.ci", "build-42.ci"));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("a*b*c", "abc"));
+        assert!(!glob_matches("a*b*c", "acb"));
+        assert!(!glob_matches("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_passphrase_settings_per_site() {
+        let config: Config = toml::from_str(
+            "[default]\npassphrase_separator = \" \"\n\n[sites.wifi]\npassphrase_words = 8\npassphrase_capitalize = true\n",
+        )
+        .unwrap();
+        assert_eq!(config.default.passphrase_trigger_prefix, "~~");
+
+        let wifi = config.get_password_config("wifi").passphrase;
+        assert_eq!(wifi.words, 8);
+        assert_eq!(wifi.separator, " ");
+        assert!(wifi.capitalize);
+        assert_eq!(config.get_password_config("other").passphrase.words, 6);
+    }
+
+    #[test]
+    fn test_parse_site_account_syntax() {
+        let legacy: Config = toml::from_str("[default]\nlength = 20\n").unwrap();
+        assert_eq!(legacy.default.version, AlgorithmVersion::V1);
+        let input = legacy.parse_site("work@github");
+        assert_eq!(input.site, "work@github");
+        assert_eq!(input.account, None);
+
+        let opted_in: Config = toml::from_str(
+            "[default]\naccount_syntax = true\n\n[sites.\"work@github\"]\naccount_syntax = false\n",
+        )
+        .unwrap();
+        let input = opted_in.parse_site("alice@github");
+        assert_eq!(input.site, "github");
+        assert_eq!(input.account, Some("alice"));
+        assert_eq!(opted_in.parse_site("work@github").site, "work@github");
+
+        let input = Config::default().parse_site("alice@github.com?pet");
+        assert_eq!(input.site, "github.com");
+        assert_eq!(input.account, Some("alice"));
+        assert_eq!(input.question, Some("pet"));
     }
 }
@@ -1,25 +1,229 @@
 use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use zeroize::Zeroize;
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::error::{EasyPasswordError, Result};
+use crate::site::normalize_site;
+use crate::ssh::SshKey;
+
+mod lesspass;
+mod policy;
+mod spectre;
+mod template;
+mod wordlist;
+
+pub use policy::PasswordPolicy;
+pub use template::DEFAULT_TEMPLATE;
 
 const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
 const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const DIGITS: &str = "0123456789";
 const SYMBOLS: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+/// Characters easily confused with one another when read or typed.
+const AMBIGUOUS: &str = "0O1lI|";
 
-const ARGON2_M_COST: u32 = 19456;
-const ARGON2_T_COST: u32 = 2;
-const ARGON2_P_COST: u32 = 1;
+pub const ARGON2_M_COST: u32 = 19456;
+pub const ARGON2_T_COST: u32 = 2;
+pub const ARGON2_P_COST: u32 = 1;
 const ENTROPY_BYTES: usize = 64;
 
+const RENDER_STREAM_LABEL: &[u8] = b"easypassword/v2/render";
+const PASSPHRASE_STREAM_LABEL: &[u8] = b"easypassword/passphrase";
+const PIN_STREAM_LABEL: &[u8] = b"easypassword/pin";
+const USERNAME_STREAM_LABEL: &[u8] = b"easypassword/username";
+const ANSWER_STREAM_LABEL: &[u8] = b"easypassword/answer";
+const SSH_KEY_STREAM_LABEL: &[u8] = b"easypassword/ssh-key";
+const OTP_KEY_STREAM_LABEL: &[u8] = b"easypassword/otp-key";
+const FINGERPRINT_STREAM_LABEL: &[u8] = b"easypassword/fingerprint";
+
+const FINGERPRINT_COLORS: [&str; 8] = [
+    "red", "orange", "yellow", "green", "teal", "blue", "purple", "pink",
+];
+const FINGERPRINT_WORDS: usize = 2;
+
+pub const MIN_PIN_LENGTH: usize = 4;
+pub const MAX_PIN_LENGTH: usize = 12;
+/// Candidates tried before giving up; each is rejected with probability < 0.1.
+const MAX_PIN_ATTEMPTS: u32 = 64;
+/// Renderings tried before a policy's positional rules are reported unsatisfiable.
+const MAX_POLICY_ATTEMPTS: u32 = 1000;
+
+/// Most common PINs from leaked PIN datasets, beyond those caught by the
+/// repetition and sequence checks.
+const COMMON_PINS: &[&str] = &[
+    "1004", "2000", "6969", "1122", "1313", "2001", "1984", "1986", "1987", "1988", "1989", "1990",
+    "1991", "2580", "0852", "5683", "0007", "1357", "2468", "112233", "159753", "131313", "696969",
+    "123321", "147258", "789456", "102030", "007007",
+];
+
+/// Version of the derivation algorithm: salt layout, KDF parameters and rendering.
+///
+/// A released version never changes its output. New behavior ships as a new
+/// version, so existing sites keep deriving the same passwords until migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum AlgorithmVersion {
+    /// SHA-256(site || counter) salt, Argon2id, rendered from the first 16
+    /// bytes of output.
+    V1,
+    /// Same salt and Argon2id as V1, rendered without bias from an HKDF-SHA256
+    /// stream over the full 64-byte output.
+    V2,
+    /// V2 with the master key, site, account and question NFKC-normalized and
+    /// their whitespace trimmed and collapsed first, so text typed on any
+    /// system derives the same bytes. ASCII input without extra whitespace
+    /// gives the same output as V2.
+    V3,
+}
+
+impl AlgorithmVersion {
+    /// Version used for newly created configs.
+    pub const LATEST: Self = Self::V3;
+
+    /// Text as this version feeds it to the derivation.
+    fn prepare(self, text: &str) -> Cow<'_, str> {
+        if self >= Self::V3 {
+            Cow::Owned(normalize_input(text))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+/// Version 1, which derived every password before versions existed, so that
+/// default settings keep their output. Configs opt into [`Self::LATEST`].
+impl Default for AlgorithmVersion {
+    fn default() -> Self {
+        Self::V1
+    }
+}
+
+impl From<AlgorithmVersion> for u32 {
+    fn from(version: AlgorithmVersion) -> Self {
+        match version {
+            AlgorithmVersion::V1 => 1,
+            AlgorithmVersion::V2 => 2,
+            AlgorithmVersion::V3 => 3,
+        }
+    }
+}
+
+impl TryFrom<u32> for AlgorithmVersion {
+    type Error = String;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            3 => Ok(Self::V3),
+            _ => Err(format!("unsupported algorithm version: {}", value)),
+        }
+    }
+}
+
+/// Argon2id cost parameters: memory in KiB, iterations and lanes.
+///
+/// Defaults follow the OWASP minimum (m=19456, t=2, p=1). Changing any cost
+/// changes every password derived with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Costs {
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for Argon2Costs {
+    fn default() -> Self {
+        Self {
+            m_cost: ARGON2_M_COST,
+            t_cost: ARGON2_T_COST,
+            p_cost: ARGON2_P_COST,
+        }
+    }
+}
+
+impl Argon2Costs {
+    fn params(&self) -> std::result::Result<Params, argon2::Error> {
+        Params::new(self.m_cost, self.t_cost, self.p_cost, Some(ENTROPY_BYTES))
+    }
+
+    /// Checks the costs against the limits enforced by `argon2::Params`.
+    pub fn validate(&self) -> Result<()> {
+        self.params().map(|_| ()).map_err(|e| {
+            EasyPasswordError::Config(format!(
+                "invalid Argon2 costs (m={}, t={}, p={}): {}",
+                self.m_cost, self.t_cost, self.p_cost, e
+            ))
+        })
+    }
+}
+
+/// What a derivation is for. Each purpose has its own salt domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Purpose<'a> {
+    Password,
+    Username,
+    /// A security answer for a question keyword, normalized.
+    Answer(&'a str),
+    SshKey,
+    /// The key that encrypts stored OTP secrets.
+    OtpKey,
+    Fingerprint,
+}
+
+/// The kind of a character class. Classes never mix kinds, so a class's kind
+/// is the kind of any of its characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClassKind {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+impl ClassKind {
+    const ALL: [Self; 4] = [Self::Lowercase, Self::Uppercase, Self::Digit, Self::Symbol];
+
+    fn of(ch: char) -> Self {
+        if ch.is_lowercase() {
+            Self::Lowercase
+        } else if ch.is_uppercase() {
+            Self::Uppercase
+        } else if ch.is_numeric() {
+            Self::Digit
+        } else {
+            Self::Symbol
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Lowercase => "lowercase",
+            Self::Uppercase => "uppercase",
+            Self::Digit => "digit",
+            Self::Symbol => "symbol",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum GenerationMode {
     Argon2id,
     Concatenation,
+    Passphrase,
+    Pin,
+    Template,
+    /// Spectre (Master Password) algorithm version 3.
+    Spectre,
+    /// LessPass version 2.
+    LessPass,
+    /// A per-site username instead of a password, derived under its own salt.
+    Username,
 }
 
 impl Default for GenerationMode {
@@ -28,6 +232,40 @@ impl Default for GenerationMode {
     }
 }
 
+/// Diceware-style passphrase settings, used by `GenerationMode::Passphrase`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphraseConfig {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+}
+
+impl Default for PassphraseConfig {
+    fn default() -> Self {
+        Self {
+            words: 6,
+            separator: "-".to_string(),
+            capitalize: false,
+        }
+    }
+}
+
+/// Username settings, used by `GenerationMode::Username`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsernameConfig {
+    pub words: usize,
+    pub digits: usize,
+}
+
+impl Default for UsernameConfig {
+    fn default() -> Self {
+        Self {
+            words: 2,
+            digits: 2,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PasswordConfig {
     pub length: usize,
@@ -35,9 +273,43 @@ pub struct PasswordConfig {
     pub use_uppercase: bool,
     pub use_digits: bool,
     pub use_symbols: bool,
+    /// Replaces the built-in symbol class, e.g. `!@#$%`.
+    pub symbol_set: Option<String>,
+    /// Characters removed from every class.
+    pub exclude: String,
+    pub avoid_ambiguous: bool,
+    /// Replaces the four built-in classes. Its characters are split into
+    /// lowercase, uppercase, digit and other classes, each of which is required.
+    pub alphabet: Option<String>,
+    pub policy: PasswordPolicy,
     pub mode: GenerationMode,
+    pub version: AlgorithmVersion,
+    pub argon2: Argon2Costs,
+    pub passphrase: PassphraseConfig,
+    pub pin_length: usize,
+    pub username: UsernameConfig,
+    /// Words in a security answer.
+    pub answer_words: usize,
+    /// Built-in template name, used when `template_pattern` is not set.
+    pub template: String,
+    /// Custom template such as `cvc-cvc-cvc9`.
+    pub template_pattern: Option<String>,
+    /// The Spectre user name; Spectre salts the master key with it.
+    pub spectre_full_name: Option<String>,
+    /// The LessPass login; LessPass salts with it. May be empty.
+    pub lesspass_login: String,
+    /// Account on the site, e.g. `alice` in `alice@github.com`. Mixed into
+    /// the salt so each account gets its own password; in LessPass mode it
+    /// stands in for the login.
+    pub account: Option<String>,
+    /// Reduce the site to its registrable domain before deriving, so
+    /// `https://www.github.com/login` gives the same password as `github.com`.
+    pub normalize_site: bool,
 }
 
+/// The defaults derive what the library always derived: version 1, with the
+/// site used as given. New configs opt into the latest version and site
+/// normalization in `config.rs`.
 impl Default for PasswordConfig {
     fn default() -> Self {
         Self {
@@ -46,39 +318,195 @@ impl Default for PasswordConfig {
             use_uppercase: true,
             use_digits: true,
             use_symbols: true,
+            symbol_set: None,
+            exclude: String::new(),
+            avoid_ambiguous: false,
+            alphabet: None,
+            policy: PasswordPolicy::default(),
             mode: GenerationMode::default(),
+            version: AlgorithmVersion::default(),
+            argon2: Argon2Costs::default(),
+            passphrase: PassphraseConfig::default(),
+            pin_length: MIN_PIN_LENGTH,
+            username: UsernameConfig::default(),
+            answer_words: 3,
+            template: DEFAULT_TEMPLATE.to_string(),
+            template_pattern: None,
+            spectre_full_name: None,
+            lesspass_login: String::new(),
+            account: None,
+            normalize_site: false,
         }
     }
 }
 
 impl PasswordConfig {
-    fn build_charset(&self) -> Vec<char> {
-        let mut charset = Vec::new();
-        if self.use_lowercase {
-            charset.extend(LOWERCASE.chars());
-        }
-        if self.use_uppercase {
-            charset.extend(UPPERCASE.chars());
+    /// The site name passwords are derived from.
+    pub fn site_name<'a>(&self, site: &'a str) -> Cow<'a, str> {
+        if self.normalize_site {
+            Cow::Owned(normalize_site(site))
+        } else {
+            Cow::Borrowed(site)
         }
-        if self.use_digits {
-            charset.extend(DIGITS.chars());
+    }
+
+    /// Checks the settings used by the configured mode without deriving anything.
+    pub fn validate(&self) -> Result<()> {
+        self.argon2.validate()?;
+        match self.mode {
+            GenerationMode::Argon2id => {
+                self.rendering_plan()?;
+            }
+            GenerationMode::Template => {
+                self.template_variants()?;
+            }
+            GenerationMode::Spectre => {
+                self.spectre_inputs()?;
+            }
+            GenerationMode::LessPass => {
+                lesspass::check(&self.lesspass_rules(), self.length)?;
+            }
+            _ => {}
         }
-        if self.use_symbols {
-            charset.extend(SYMBOLS.chars());
+        Ok(())
+    }
+
+    fn template_variants(&self) -> Result<Vec<&str>> {
+        match &self.template_pattern {
+            Some(pattern) if pattern.is_empty() => Err(EasyPasswordError::PasswordGeneration(
+                "template_pattern must not be empty".to_string(),
+            )),
+            Some(pattern) => Ok(vec![pattern.as_str()]),
+            None => Ok(template::named(&self.template)?.to_vec()),
         }
-        charset
     }
 
-    fn count_enabled_charsets(&self) -> usize {
+    /// LessPass rules in LessPass order, from the character class flags.
+    fn lesspass_rules(&self) -> Vec<&'static str> {
         [
-            self.use_lowercase,
-            self.use_uppercase,
-            self.use_digits,
-            self.use_symbols,
+            (self.use_lowercase, LOWERCASE),
+            (self.use_uppercase, UPPERCASE),
+            (self.use_digits, DIGITS),
+            (self.use_symbols, SYMBOLS),
         ]
-        .iter()
-        .filter(|&&x| x)
-        .count()
+        .into_iter()
+        .filter_map(|(enabled, rule)| enabled.then_some(rule))
+        .collect()
+    }
+
+    /// The full name and template variants for Spectre mode, which only
+    /// supports the built-in templates.
+    fn spectre_inputs(&self) -> Result<(&str, &'static [&'static str])> {
+        let full_name = self
+            .spectre_full_name
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| {
+                EasyPasswordError::PasswordGeneration(
+                    "Spectre mode needs spectre_full_name".to_string(),
+                )
+            })?;
+        if self.template_pattern.is_some() {
+            return Err(EasyPasswordError::PasswordGeneration(
+                "Spectre mode only supports the built-in templates".to_string(),
+            ));
+        }
+        Ok((full_name, template::named(&self.template)?))
+    }
+
+    /// The character classes and length to render, after checking that the
+    /// version and policy can produce them.
+    fn rendering_plan(&self) -> Result<(Vec<Vec<char>>, usize)> {
+        let classes = self.character_classes()?;
+        match self.version {
+            AlgorithmVersion::V1 => {
+                if !self.policy.is_empty() {
+                    return Err(EasyPasswordError::PolicyUnsatisfiable(
+                        "password policies require algorithm version 2 or later".to_string(),
+                    ));
+                }
+                Ok((classes, self.length))
+            }
+            AlgorithmVersion::V2 | AlgorithmVersion::V3 => {
+                let length = self.policy.effective_length(self.length);
+                let required_count = classes.len();
+                if length < required_count {
+                    return Err(EasyPasswordError::PasswordGeneration(format!(
+                        "Length must be at least {} to include every enabled character class",
+                        required_count
+                    )));
+                }
+                self.policy.check(&classes, length)?;
+                Ok((classes, length))
+            }
+        }
+    }
+
+    /// The required character classes, in rendering order, after exclusions.
+    fn character_classes(&self) -> Result<Vec<Vec<char>>> {
+        let classes: Vec<Vec<char>> = match &self.alphabet {
+            Some(alphabet) => {
+                let mut classes = vec![Vec::new(); ClassKind::ALL.len()];
+                for ch in alphabet.chars() {
+                    let class = ClassKind::of(ch) as usize;
+                    if !classes[class].contains(&ch) {
+                        classes[class].push(ch);
+                    }
+                }
+                classes.retain(|class| !class.is_empty());
+                classes
+            }
+            None => {
+                let symbols = self.symbol_set.as_deref().unwrap_or(SYMBOLS);
+                if let Some(ch) = symbols
+                    .chars()
+                    .find(|c| c.is_alphanumeric() || c.is_whitespace())
+                {
+                    return Err(EasyPasswordError::PasswordGeneration(format!(
+                        "symbol_set may only contain symbols, found {:?}",
+                        ch
+                    )));
+                }
+                [
+                    (self.use_lowercase, LOWERCASE),
+                    (self.use_uppercase, UPPERCASE),
+                    (self.use_digits, DIGITS),
+                    (self.use_symbols, symbols),
+                ]
+                .into_iter()
+                .filter_map(|(enabled, class)| enabled.then_some(class))
+                .map(|class| {
+                    let mut chars: Vec<char> = Vec::new();
+                    for ch in class.chars() {
+                        if !chars.contains(&ch) {
+                            chars.push(ch);
+                        }
+                    }
+                    chars
+                })
+                .collect()
+            }
+        };
+
+        let excluded = |ch: &char| {
+            self.exclude.contains(*ch) || (self.avoid_ambiguous && AMBIGUOUS.contains(*ch))
+        };
+        let classes: Vec<Vec<char>> = classes
+            .into_iter()
+            .map(|class| class.into_iter().filter(|ch| !excluded(ch)).collect())
+            .collect();
+
+        if classes.is_empty() {
+            return Err(EasyPasswordError::PasswordGeneration(
+                "At least one character class must be enabled".to_string(),
+            ));
+        }
+        if classes.iter().any(|class| class.is_empty()) {
+            return Err(EasyPasswordError::PasswordGeneration(
+                "A character class is empty after exclusions".to_string(),
+            ));
+        }
+        Ok(classes)
     }
 }
 
@@ -88,43 +516,330 @@ pub fn generate_password(
     counter: u32,
     config: &PasswordConfig,
 ) -> Result<String> {
-    if config.mode == GenerationMode::Concatenation {
-        // Concatenation mode is intentionally simple: no implicit separator.
-        // If you want a separator (e.g. '!'), include it in the master_key.
-        return Ok(format!("{}{}", master_key, site));
+    let site_name = config.site_name(site);
+    match config.mode {
+        GenerationMode::Concatenation => {
+            // Concatenation mode is intentionally simple: no implicit separator
+            // and the site exactly as typed. If you want a separator (e.g. '!'),
+            // include it in the master_key.
+            Ok(format!("{}{}", master_key, site))
+        }
+        GenerationMode::Passphrase => {
+            if config.passphrase.words == 0 {
+                return Err(EasyPasswordError::PasswordGeneration(
+                    "A passphrase needs at least one word".to_string(),
+                ));
+            }
+            derive_and_render(
+                master_key,
+                Purpose::Password,
+                &site_name,
+                counter,
+                config,
+                |entropy| render_passphrase(entropy, &config.passphrase),
+            )
+        }
+        GenerationMode::Template => {
+            let variants = config.template_variants()?;
+            derive_and_render(
+                master_key,
+                Purpose::Password,
+                &site_name,
+                counter,
+                config,
+                |entropy| template::render(entropy, &variants),
+            )
+        }
+        GenerationMode::Spectre => {
+            let (full_name, variants) = config.spectre_inputs()?;
+            let mut key = spectre::master_key(full_name, master_key)?;
+            let password = spectre::site_password(&key, &site_name, counter, variants);
+            key.zeroize();
+            Ok(password)
+        }
+        GenerationMode::LessPass => {
+            let mut entropy = lesspass::entropy(
+                master_key,
+                &site_name,
+                config.account.as_deref().unwrap_or(&config.lesspass_login),
+                counter,
+            );
+            let password = lesspass::render(&entropy, &config.lesspass_rules(), config.length);
+            entropy.zeroize();
+            password
+        }
+        GenerationMode::Username => {
+            if config.username.words == 0 {
+                return Err(EasyPasswordError::PasswordGeneration(
+                    "A username needs at least one word".to_string(),
+                ));
+            }
+            derive_and_render(
+                master_key,
+                Purpose::Username,
+                &site_name,
+                counter,
+                config,
+                |entropy| render_username(entropy, &config.username),
+            )
+        }
+        GenerationMode::Pin => {
+            if !(MIN_PIN_LENGTH..=MAX_PIN_LENGTH).contains(&config.pin_length) {
+                return Err(EasyPasswordError::PasswordGeneration(format!(
+                    "PIN length must be between {} and {}",
+                    MIN_PIN_LENGTH, MAX_PIN_LENGTH
+                )));
+            }
+            derive_and_render(
+                master_key,
+                Purpose::Password,
+                &site_name,
+                counter,
+                config,
+                |entropy| render_pin(entropy, config.pin_length),
+            )?
+        }
+        GenerationMode::Argon2id => {
+            generate_charset_password(master_key, &site_name, counter, config)
+        }
     }
+}
 
-    let charset = config.build_charset();
-    if charset.is_empty() {
+/// Derives the answer to a site's security question, e.g. `pet` for
+/// "What was your first pet's name?".
+///
+/// Answers use their own salt domain and ignore the site counter, so rotating
+/// a password keeps the answers on file with the site valid.
+pub fn generate_answer(
+    master_key: &str,
+    site: &str,
+    question: &str,
+    config: &PasswordConfig,
+) -> Result<String> {
+    if config.answer_words == 0 {
         return Err(EasyPasswordError::PasswordGeneration(
-            "At least one character class must be enabled".to_string(),
+            "An answer needs at least one word".to_string(),
         ));
     }
+    let question = config.version.prepare(question.trim()).to_lowercase();
+    if question.is_empty() {
+        return Err(EasyPasswordError::PasswordGeneration(
+            "A security question keyword must not be empty".to_string(),
+        ));
+    }
+
+    derive_and_render(
+        master_key,
+        Purpose::Answer(&question),
+        &config.site_name(site),
+        0,
+        config,
+        |entropy| {
+            let words = wordlist::words();
+            let mut stream = EntropyStream::new(entropy, ANSWER_STREAM_LABEL);
+            (0..config.answer_words)
+                .map(|_| words[stream.next_index(wordlist::WORD_COUNT)])
+                .collect::<Vec<_>>()
+                .join(" ")
+        },
+    )
+}
 
-    let site_normalized = site.to_lowercase();
-    let salt = build_salt(&site_normalized, counter);
-    let mut entropy = derive_entropy(master_key, &salt)?;
-    let password = render_password(&entropy, &charset, config);
+/// Derives an Ed25519 SSH key for `label`, e.g. `github` or `work-laptop`.
+///
+/// The key has its own salt domain; the label is used like a site name, so
+/// bumping its counter gives a new key. Like the OTP key it ignores every
+/// other setting, so the site's options cannot change it. The passphrase for
+/// the encrypted key file is a passphrase rendered from the same derivation.
+pub fn generate_ssh_key(master_key: &str, label: &str, counter: u32) -> Result<SshKey> {
+    let config = pinned_config();
+    derive_and_render(
+        master_key,
+        Purpose::SshKey,
+        label,
+        counter,
+        &config,
+        |entropy| {
+            let mut stream = EntropyStream::new(entropy, SSH_KEY_STREAM_LABEL);
+            let mut seed = [0u8; 32];
+            let mut kdf_salt = [0u8; 16];
+            let mut checkint = [0u8; 4];
+            stream.fill(&mut seed);
+            stream.fill(&mut kdf_salt);
+            stream.fill(&mut checkint);
+            let key = SshKey::new(
+                seed,
+                label.to_string(),
+                render_passphrase(entropy, &config.passphrase),
+                kdf_salt,
+                u32::from_be_bytes(checkint),
+            );
+            seed.zeroize();
+            key
+        },
+    )
+}
+
+/// Derives the key that encrypts OTP secrets in the config. It always uses
+/// the default Argon2id costs, so tuning the costs later cannot make the
+/// stored secrets unreadable.
+pub fn generate_otp_key(master_key: &str) -> Result<Zeroizing<[u8; 32]>> {
+    let config = pinned_config();
+    derive_and_render(master_key, Purpose::OtpKey, "", 0, &config, |entropy| {
+        let mut key = Zeroizing::new([0u8; 32]);
+        EntropyStream::new(entropy, OTP_KEY_STREAM_LABEL).fill(key.as_mut_slice());
+        key
+    })
+}
+
+/// A short fingerprint of the master key, e.g. `pink coveting-swoosh`, for spotting
+/// a mistyped key at a glance. Like the OTP key it uses the default Argon2id
+/// costs. It holds only about 29 bits, enough to catch typos while revealing
+/// little about the key itself.
+pub fn generate_fingerprint(master_key: &str) -> Result<String> {
+    let config = pinned_config();
+    derive_and_render(
+        master_key,
+        Purpose::Fingerprint,
+        "",
+        0,
+        &config,
+        |entropy| {
+            let words = wordlist::words();
+            let mut stream = EntropyStream::new(entropy, FINGERPRINT_STREAM_LABEL);
+            let color = FINGERPRINT_COLORS[stream.next_index(FINGERPRINT_COLORS.len())];
+            let words: Vec<&str> = (0..FINGERPRINT_WORDS)
+                .map(|_| words[stream.next_index(wordlist::WORD_COUNT)])
+                .collect();
+            format!("{} {}", color, words.join("-"))
+        },
+    )
+}
+
+/// Settings for derivations that must never change with the defaults: the
+/// default Argon2id costs, and version 3 so the master key is normalized the
+/// same way on every system.
+fn pinned_config() -> PasswordConfig {
+    PasswordConfig {
+        version: AlgorithmVersion::V3,
+        ..Default::default()
+    }
+}
+
+fn generate_charset_password(
+    master_key: &str,
+    site: &str,
+    counter: u32,
+    config: &PasswordConfig,
+) -> Result<String> {
+    let (classes, length) = config.rendering_plan()?;
+
+    match config.version {
+        AlgorithmVersion::V1 => derive_and_render(
+            master_key,
+            Purpose::Password,
+            site,
+            counter,
+            config,
+            |entropy| render_password(entropy, &classes, length),
+        ),
+        AlgorithmVersion::V2 | AlgorithmVersion::V3 => derive_and_render(
+            master_key,
+            Purpose::Password,
+            site,
+            counter,
+            config,
+            |entropy| render_password_v2(entropy, &classes, length, &config.policy),
+        )?,
+    }
+}
+
+/// Derives the Argon2id output for a site, renders it, and wipes it.
+fn derive_and_render<T>(
+    master_key: &str,
+    purpose: Purpose,
+    site: &str,
+    counter: u32,
+    config: &PasswordConfig,
+    render: impl FnOnce(&[u8]) -> T,
+) -> Result<T> {
+    let version = config.version;
+    let master_key = Zeroizing::new(version.prepare(master_key).into_owned());
+    // Lowercase after normalizing, since NFKC can fold a character into an
+    // uppercase letter, e.g. `ℌ` into `H`.
+    let site_normalized = version.prepare(site).to_lowercase();
+    let account = config
+        .account
+        .as_deref()
+        .map(|account| version.prepare(account).to_lowercase());
+    let salt = build_salt(purpose, &site_normalized, account.as_deref(), counter);
+    let mut entropy = derive_entropy(&master_key, &salt, &config.argon2)?;
+    let output = render(&entropy);
     entropy.zeroize();
+    Ok(output)
+}
+
+/// NFKC-normalizes text and trims it, collapsing each run of whitespace into
+/// one space. NFKC also folds compatibility forms such as full-width letters
+/// and ligatures, so `ｇｉｔｈｕｂ` is `github`.
+fn normalize_input(text: &str) -> String {
+    let normalized: Zeroizing<String> = Zeroizing::new(text.nfkc().collect());
+    let mut words = normalized.split_whitespace();
+    let mut output = String::with_capacity(normalized.len());
+    if let Some(first) = words.next() {
+        output.push_str(first);
+        for word in words {
+            output.push(' ');
+            output.push_str(word);
+        }
+    }
+    output
+}
 
-    Ok(password)
+/// Passwords keep the original, untagged `SHA256(site || counter)` salt.
+/// Every other purpose hashes a distinct tag and length-prefixed fields, so
+/// salts are domain-separated by tag: two purposes only share a salt through
+/// a SHA-256 collision.
+fn build_salt(purpose: Purpose, site: &str, account: Option<&str>, counter: u32) -> Vec<u8> {
+    let counter_be = counter.to_be_bytes();
+    let (tag, mut fields): (&[u8], Vec<&[u8]>) = match purpose {
+        // Passwords without an account keep the original salt.
+        Purpose::Password if account.is_none() => {
+            let mut hasher = Sha256::new();
+            hasher.update(site.as_bytes());
+            hasher.update(counter.to_le_bytes());
+            return hasher.finalize().to_vec();
+        }
+        Purpose::Password => (b"easypassword/password", vec![site.as_bytes(), &counter_be]),
+        Purpose::Username => (b"easypassword/username", vec![site.as_bytes(), &counter_be]),
+        Purpose::Answer(question) => (
+            b"easypassword/answer",
+            vec![site.as_bytes(), question.as_bytes()],
+        ),
+        Purpose::SshKey => (b"easypassword/ssh-key", vec![site.as_bytes(), &counter_be]),
+        Purpose::OtpKey => (b"easypassword/otp-key", vec![]),
+        Purpose::Fingerprint => (b"easypassword/fingerprint", vec![]),
+    };
+    fields.extend(account.map(str::as_bytes));
+    tagged_salt(tag, &fields)
 }
 
-fn build_salt(site: &str, counter: u32) -> Vec<u8> {
+/// `SHA256(tag || 0x00 || (len(field) as u32 big-endian || field)...)`
+fn tagged_salt(tag: &[u8], fields: &[&[u8]]) -> Vec<u8> {
     let mut hasher = Sha256::new();
-    hasher.update(site.as_bytes());
-    hasher.update(counter.to_le_bytes());
+    hasher.update(tag);
+    hasher.update([0u8]);
+    for field in fields {
+        hasher.update((field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
     hasher.finalize().to_vec()
 }
 
-fn derive_entropy(master_key: &str, salt: &[u8]) -> Result<Vec<u8>> {
-    let params = Params::new(
-        ARGON2_M_COST,
-        ARGON2_T_COST,
-        ARGON2_P_COST,
-        Some(ENTROPY_BYTES),
-    )
-    .map_err(|e| EasyPasswordError::PasswordGeneration(e.to_string()))?;
+pub fn derive_entropy(master_key: &str, salt: &[u8], costs: &Argon2Costs) -> Result<Vec<u8>> {
+    let params = costs
+        .params()
+        .map_err(|e| EasyPasswordError::PasswordGeneration(e.to_string()))?;
 
     let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
     let mut entropy = vec![0u8; ENTROPY_BYTES];
@@ -136,13 +851,13 @@ fn derive_entropy(master_key: &str, salt: &[u8]) -> Result<Vec<u8>> {
     Ok(entropy)
 }
 
-fn render_password(entropy: &[u8], charset: &[char], config: &PasswordConfig) -> String {
+fn render_password(entropy: &[u8], classes: &[Vec<char>], length: usize) -> String {
     let mut quotient = bytes_to_big_uint(entropy);
+    let charset = classes.concat();
     let charset_len = charset.len() as u128;
-    let required_count = config.count_enabled_charsets();
-    let base_length = config.length.saturating_sub(required_count);
+    let base_length = length.saturating_sub(classes.len());
 
-    let mut password: Vec<char> = Vec::with_capacity(config.length);
+    let mut password: Vec<char> = Vec::with_capacity(length);
 
     for _ in 0..base_length {
         let (new_quotient, remainder) = div_mod(quotient, charset_len);
@@ -151,24 +866,9 @@ fn render_password(entropy: &[u8], charset: &[char], config: &PasswordConfig) ->
     }
 
     let mut required_chars = Vec::new();
-    if config.use_lowercase {
-        let (new_quotient, remainder) = div_mod(quotient, LOWERCASE.len() as u128);
-        required_chars.push(LOWERCASE.chars().nth(remainder as usize).unwrap());
-        quotient = new_quotient;
-    }
-    if config.use_uppercase {
-        let (new_quotient, remainder) = div_mod(quotient, UPPERCASE.len() as u128);
-        required_chars.push(UPPERCASE.chars().nth(remainder as usize).unwrap());
-        quotient = new_quotient;
-    }
-    if config.use_digits {
-        let (new_quotient, remainder) = div_mod(quotient, DIGITS.len() as u128);
-        required_chars.push(DIGITS.chars().nth(remainder as usize).unwrap());
-        quotient = new_quotient;
-    }
-    if config.use_symbols {
-        let (new_quotient, remainder) = div_mod(quotient, SYMBOLS.len() as u128);
-        required_chars.push(SYMBOLS.chars().nth(remainder as usize).unwrap());
+    for class in classes {
+        let (new_quotient, remainder) = div_mod(quotient, class.len() as u128);
+        required_chars.push(class[remainder as usize]);
         quotient = new_quotient;
     }
 
@@ -185,6 +885,207 @@ fn render_password(entropy: &[u8], charset: &[char], config: &PasswordConfig) ->
     password.into_iter().collect()
 }
 
+/// Renders the required characters of each class plus uniform fill from the
+/// full charset, then shuffles. Every choice is an unbiased draw from the stream.
+///
+/// If the result breaks the policy's positional rules, rendering is repeated
+/// with streams for sub-counters 1, 2, ... Without a policy the first rendering
+/// always stands.
+fn render_password_v2(
+    entropy: &[u8],
+    classes: &[Vec<char>],
+    length: usize,
+    policy: &PasswordPolicy,
+) -> Result<String> {
+    let charset = classes.concat();
+
+    for attempt in 0..MAX_POLICY_ATTEMPTS {
+        let mut stream = if attempt == 0 {
+            EntropyStream::new(entropy, RENDER_STREAM_LABEL)
+        } else {
+            EntropyStream::with_context(entropy, RENDER_STREAM_LABEL, &attempt.to_be_bytes())
+        };
+        let mut password: Vec<char> = Vec::with_capacity(length);
+
+        for class in classes {
+            for _ in 0..policy.required_count(ClassKind::of(class[0])) {
+                password.push(class[stream.next_index(class.len())]);
+            }
+        }
+        while password.len() < length {
+            password.push(charset[stream.next_index(charset.len())]);
+        }
+        stream.shuffle(&mut password);
+
+        if policy.accepts(&password) {
+            return Ok(password.into_iter().collect());
+        }
+    }
+
+    Err(EasyPasswordError::PolicyUnsatisfiable(format!(
+        "no password met the policy after {} attempts",
+        MAX_POLICY_ATTEMPTS
+    )))
+}
+
+fn render_passphrase(entropy: &[u8], config: &PassphraseConfig) -> String {
+    let words = wordlist::words();
+    let mut stream = EntropyStream::new(entropy, PASSPHRASE_STREAM_LABEL);
+
+    (0..config.words)
+        .map(|_| {
+            let word = words[stream.next_index(wordlist::WORD_COUNT)];
+            if config.capitalize {
+                capitalize(word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(&config.separator)
+}
+
+/// Lowercase words run together, then digits, e.g. `cornrobust42`.
+fn render_username(entropy: &[u8], config: &UsernameConfig) -> String {
+    let words = wordlist::words();
+    let mut stream = EntropyStream::new(entropy, USERNAME_STREAM_LABEL);
+
+    let mut username = String::new();
+    for _ in 0..config.words {
+        username.push_str(words[stream.next_index(wordlist::WORD_COUNT)]);
+    }
+    for _ in 0..config.digits {
+        username.push(DIGITS.as_bytes()[stream.next_index(DIGITS.len())] as char);
+    }
+    username
+}
+
+/// Draws candidate PINs under sub-counters 0, 1, 2, ... and returns the first
+/// one that is not trivially guessable.
+fn render_pin(entropy: &[u8], length: usize) -> Result<String> {
+    (0..MAX_PIN_ATTEMPTS)
+        .map(|attempt| pin_candidate(entropy, attempt, length))
+        .find(|pin| !is_weak_pin(pin))
+        .ok_or_else(|| EasyPasswordError::PasswordGeneration("No acceptable PIN found".to_string()))
+}
+
+fn pin_candidate(entropy: &[u8], attempt: u32, length: usize) -> String {
+    let mut stream = EntropyStream::with_context(entropy, PIN_STREAM_LABEL, &attempt.to_be_bytes());
+    (0..length)
+        .map(|_| DIGITS.as_bytes()[stream.next_index(DIGITS.len())] as char)
+        .collect()
+}
+
+/// All-same digits and other short repeating patterns (`1212`), straight
+/// ascending or descending runs (`1234`, `8765`, `8901`), and common PINs.
+fn is_weak_pin(pin: &str) -> bool {
+    let digits: Vec<u8> = pin.bytes().map(|b| b - b'0').collect();
+    let len = digits.len();
+
+    let repeating = (1..len)
+        .filter(|period| len.is_multiple_of(*period))
+        .any(|period| digits[period..] == digits[..len - period]);
+    let steps = |step: u8| digits.windows(2).all(|w| (w[0] + step) % 10 == w[1]);
+
+    repeating || steps(1) || steps(9) || COMMON_PINS.contains(&pin)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// An unbounded byte stream expanded from KDF output with HKDF-SHA256.
+///
+/// Block `i` is `HKDF-Expand(PRK, label || i as u64 big-endian, 32)`.
+pub(crate) struct EntropyStream {
+    hkdf: Hkdf<Sha256>,
+    info: Vec<u8>,
+    block: [u8; 32],
+    block_index: u64,
+    pos: usize,
+}
+
+impl EntropyStream {
+    pub(crate) fn new(entropy: &[u8], label: &[u8]) -> Self {
+        Self::with_context(entropy, label, &[])
+    }
+
+    /// A stream whose HKDF info is `label || context || i`, independent of the
+    /// streams for other contexts.
+    pub(crate) fn with_context(entropy: &[u8], label: &[u8], context: &[u8]) -> Self {
+        Self {
+            hkdf: Hkdf::<Sha256>::new(None, entropy),
+            info: [label, context].concat(),
+            block: [0u8; 32],
+            block_index: 0,
+            pos: 32,
+        }
+    }
+
+    fn next_byte(&mut self) -> u8 {
+        if self.pos == self.block.len() {
+            self.hkdf
+                .expand_multi_info(
+                    &[&self.info, &self.block_index.to_be_bytes()],
+                    &mut self.block,
+                )
+                .expect("32 bytes is a valid HKDF-SHA256 output length");
+            self.block_index += 1;
+            self.pos = 0;
+        }
+        let byte = self.block[self.pos];
+        self.pos += 1;
+        byte
+    }
+
+    pub(crate) fn fill(&mut self, out: &mut [u8]) {
+        for byte in out.iter_mut() {
+            *byte = self.next_byte();
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let bytes = [
+            self.next_byte(),
+            self.next_byte(),
+            self.next_byte(),
+            self.next_byte(),
+        ];
+        u32::from_be_bytes(bytes)
+    }
+
+    /// Uniform index in `0..n`, rejecting draws from the incomplete last bucket.
+    pub(crate) fn next_index(&mut self, n: usize) -> usize {
+        assert!(n > 0 && n <= u32::MAX as usize, "index range out of bounds");
+        let n = n as u32;
+        let zone = u32::MAX - (u32::MAX % n + 1) % n;
+        loop {
+            let value = self.next_u32();
+            if value <= zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+
+    /// Fisher-Yates shuffle driven by the stream.
+    pub(crate) fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.next_index(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+impl Drop for EntropyStream {
+    fn drop(&mut self) {
+        self.block.zeroize();
+    }
+}
+
 fn bytes_to_big_uint(bytes: &[u8]) -> u128 {
     let mut result: u128 = 0;
     for &byte in bytes.iter().take(16) {
@@ -243,6 +1144,750 @@ mod tests {
         assert_ne!(pw1, pw2);
     }
 
+    // Frozen outputs of version 1. If any of these change, every existing
+    // version 1 password has changed with them.
+    #[test]
+    fn test_v1_known_answers() {
+        let v1 = PasswordConfig {
+            version: AlgorithmVersion::V1,
+            ..Default::default()
+        };
+        let cases = [
+            (
+                "master",
+                "github.com",
+                1,
+                16,
+                true,
+                true,
+                true,
+                "RQMT`Gl*q:$3*xqM",
+            ),
+            (
+                "correct horse battery staple",
+                "example.org",
+                3,
+                16,
+                true,
+                true,
+                true,
+                "]NP1[kbzkI/WF/RQ",
+            ),
+            (
+                "master",
+                "github.com",
+                1,
+                20,
+                true,
+                true,
+                false,
+                "jTfHMgbv6vqp6cz5KQzo",
+            ),
+            ("master", "bank", 2, 6, false, false, false, "169154"),
+            (
+                "master",
+                "github.com",
+                1,
+                32,
+                true,
+                true,
+                true,
+                "!0AaRQMT`l*q:$qMHB</o]ndaaaaaaaa",
+            ),
+        ];
+
+        for (master, site, counter, length, lower, upper, symbols, expected) in cases {
+            let config = PasswordConfig {
+                length,
+                use_lowercase: lower,
+                use_uppercase: upper,
+                use_symbols: symbols,
+                ..v1.clone()
+            };
+            let pw = generate_password(master, site, counter, &config).unwrap();
+            assert_eq!(pw, expected, "site={} counter={}", site, counter);
+        }
+
+        // The default settings are still version 1 with the site as given.
+        let default = PasswordConfig::default();
+        assert_eq!(
+            generate_password("master", "github.com", 1, &default).unwrap(),
+            "RQMT`Gl*q:$3*xqM"
+        );
+        assert_ne!(
+            generate_password("master", "https://www.github.com", 1, &default).unwrap(),
+            "RQMT`Gl*q:$3*xqM"
+        );
+    }
+
+    #[test]
+    fn test_v2_known_answers() {
+        let v2 = PasswordConfig {
+            version: AlgorithmVersion::V2,
+            ..Default::default()
+        };
+        let pw = generate_password("master", "github.com", 1, &v2).unwrap();
+        assert_eq!(pw, "-D/{]ao]PZhn50f8");
+
+        let config = PasswordConfig {
+            length: 32,
+            ..v2.clone()
+        };
+        let pw = generate_password("master", "github.com", 1, &config).unwrap();
+        assert_eq!(pw, "f5h8]D-fe`n/fI5Z~<P{<,_O0Z]oma$r");
+    }
+
+    #[test]
+    fn test_v2_rejects_length_below_class_count() {
+        let config = PasswordConfig {
+            length: 3,
+            version: AlgorithmVersion::V2,
+            ..Default::default()
+        };
+        assert!(generate_password("master", "github.com", 1, &config).is_err());
+    }
+
+    #[test]
+    fn test_v2_long_password_distribution() {
+        let config = PasswordConfig {
+            length: 94 * 200,
+            version: AlgorithmVersion::V2,
+            ..Default::default()
+        };
+        let classes = config.character_classes().unwrap();
+        let charset = classes.concat();
+        let entropy: Vec<u8> = (0..ENTROPY_BYTES as u8).collect();
+        let password: Vec<char> =
+            render_password_v2(&entropy, &classes, config.length, &config.policy)
+                .unwrap()
+                .chars()
+                .collect();
+        assert_eq!(password.len(), config.length);
+
+        // Chi-square goodness of fit against a uniform distribution over 94
+        // characters (93 degrees of freedom; 150 is far beyond p = 0.0001).
+        let expected = (config.length / charset.len()) as f64;
+        let chi_square: f64 = charset
+            .iter()
+            .map(|c| {
+                let observed = password.iter().filter(|&p| p == c).count() as f64;
+                (observed - expected).powi(2) / expected
+            })
+            .sum();
+        assert!(chi_square < 150.0, "chi-square too high: {}", chi_square);
+
+        // The tail must not collapse into a repeated character the way v1 does.
+        let tail = &password[password.len() - 16..];
+        assert!(tail.iter().any(|c| *c != tail[0]));
+    }
+
+    #[test]
+    fn test_entropy_stream_index_bounds() {
+        let mut stream = EntropyStream::new(&[1, 2, 3], b"test");
+        for n in [1, 2, 3, 10, 94, 7776, 1 << 31] {
+            for _ in 0..100 {
+                assert!(stream.next_index(n) < n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_argon2_costs_change_password() {
+        let config = PasswordConfig::default();
+        let stronger = PasswordConfig {
+            argon2: Argon2Costs {
+                m_cost: 32768,
+                t_cost: 3,
+                p_cost: 1,
+            },
+            ..Default::default()
+        };
+        let pw1 = generate_password("master", "github.com", 1, &config).unwrap();
+        let pw2 = generate_password("master", "github.com", 1, &stronger).unwrap();
+        assert_ne!(pw1, pw2);
+    }
+
+    #[test]
+    fn test_argon2_costs_validation() {
+        assert!(Argon2Costs::default().validate().is_ok());
+        let too_little_memory = Argon2Costs {
+            m_cost: 4,
+            ..Default::default()
+        };
+        assert!(too_little_memory.validate().is_err());
+        let no_iterations = Argon2Costs {
+            t_cost: 0,
+            ..Default::default()
+        };
+        assert!(no_iterations.validate().is_err());
+    }
+
+    #[test]
+    fn test_v3_normalizes_unicode() {
+        // Site normalization would compose the site itself; test the version alone.
+        let v2 = PasswordConfig {
+            version: AlgorithmVersion::V2,
+            normalize_site: false,
+            ..Default::default()
+        };
+        let v3 = PasswordConfig {
+            version: AlgorithmVersion::V3,
+            normalize_site: false,
+            ..Default::default()
+        };
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+
+        let nfc = generate_password(composed, "github.com", 1, &v3).unwrap();
+        assert_eq!(
+            nfc,
+            generate_password(decomposed, "github.com", 1, &v3).unwrap()
+        );
+        assert_ne!(
+            generate_password(composed, "github.com", 1, &v2).unwrap(),
+            generate_password(decomposed, "github.com", 1, &v2).unwrap()
+        );
+        // V2 already used composed input as is.
+        assert_eq!(
+            nfc,
+            generate_password(composed, "github.com", 1, &v2).unwrap()
+        );
+
+        let site = "b\u{fc}cher";
+        assert_eq!(
+            generate_password("master", site, 1, &v3).unwrap(),
+            generate_password("master", "bu\u{308}cher", 1, &v3).unwrap()
+        );
+        assert_ne!(
+            generate_password("master", site, 1, &v2).unwrap(),
+            generate_password("master", "bu\u{308}cher", 1, &v2).unwrap()
+        );
+        assert_eq!(
+            generate_answer("master", site, "caf\u{e9}", &v3).unwrap(),
+            generate_answer("master", site, decomposed, &v3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_v3_whitespace_and_compatibility_forms() {
+        let v3 = PasswordConfig {
+            version: AlgorithmVersion::V3,
+            ..Default::default()
+        };
+        let expected = generate_password("correct horse", "github", 1, &v3).unwrap();
+        for key in [
+            " correct horse ",
+            "correct\thorse",
+            "correct\u{a0}\u{a0}horse",
+        ] {
+            assert_eq!(generate_password(key, "github", 1, &v3).unwrap(), expected);
+        }
+        assert_eq!(
+            generate_password(
+                "correct horse",
+                "\u{ff47}\u{ff49}\u{ff54}\u{ff48}\u{ff55}\u{ff42}",
+                1,
+                &v3
+            )
+            .unwrap(),
+            expected
+        );
+        // U+210C folds to an uppercase `H`, which must still be lowercased.
+        assert_eq!(
+            generate_password("correct horse", "git\u{210c}ub", 1, &v3).unwrap(),
+            expected
+        );
+        let account = |account: &str| PasswordConfig {
+            account: Some(account.to_string()),
+            ..v3.clone()
+        };
+        assert_eq!(
+            generate_password("correct horse", "github", 1, &account("\u{210c}ome")).unwrap(),
+            generate_password("correct horse", "github", 1, &account("home")).unwrap()
+        );
+        assert_eq!(normalize_input("\u{fb01}le  name\n"), "file name");
+        assert_eq!(normalize_input("   "), "");
+
+        let v2 = PasswordConfig {
+            version: AlgorithmVersion::V2,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password("master", "github.com", 1, &v2).unwrap(),
+            generate_password("master", "github.com", 1, &v3).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_algorithm_version_numbers() {
+        assert_eq!(u32::from(AlgorithmVersion::V1), 1);
+        assert_eq!(u32::from(AlgorithmVersion::V2), 2);
+        assert_eq!(AlgorithmVersion::try_from(1), Ok(AlgorithmVersion::V1));
+        assert_eq!(u32::from(AlgorithmVersion::V3), 3);
+        assert_eq!(AlgorithmVersion::try_from(2), Ok(AlgorithmVersion::V2));
+        assert_eq!(AlgorithmVersion::try_from(3), Ok(AlgorithmVersion::V3));
+        assert!(AlgorithmVersion::try_from(0).is_err());
+        assert!(AlgorithmVersion::try_from(4).is_err());
+    }
+
+    #[test]
+    fn test_passphrase_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Passphrase,
+            ..Default::default()
+        };
+        let phrase = generate_password("master", "wifi", 1, &config).unwrap();
+        assert_eq!(phrase, "pancake-chimp-drown-theorize-entail-taste");
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Passphrase,
+            passphrase: PassphraseConfig {
+                words: 4,
+                separator: " ".to_string(),
+                capitalize: true,
+            },
+            ..Default::default()
+        };
+        let phrase = generate_password("master", "wifi", 1, &config).unwrap();
+        let words: Vec<&str> = phrase.split(' ').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert!(words
+            .iter()
+            .all(|w| wordlist::words().contains(&w.to_lowercase().as_str())));
+    }
+
+    #[test]
+    fn test_passphrase_requires_words() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Passphrase,
+            passphrase: PassphraseConfig {
+                words: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(generate_password("master", "wifi", 1, &config).is_err());
+    }
+
+    #[test]
+    fn test_pin_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Pin,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password("master", "bank", 1, &config).unwrap(),
+            "0128"
+        );
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Pin,
+            pin_length: 8,
+            ..Default::default()
+        };
+        let pin = generate_password("master", "bank", 1, &config).unwrap();
+        assert_eq!(pin.len(), 8);
+        assert!(pin.bytes().all(|b| b.is_ascii_digit()));
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Pin,
+            pin_length: 3,
+            ..Default::default()
+        };
+        assert!(generate_password("master", "bank", 1, &config).is_err());
+    }
+
+    #[test]
+    fn test_weak_pins_rejected() {
+        for pin in [
+            "0000", "1111", "1212", "123123", "1234", "4321", "8901", "2109", "2580",
+        ] {
+            assert!(is_weak_pin(pin), "{} should be weak", pin);
+        }
+        for pin in ["7305", "1235", "0921", "482915"] {
+            assert!(!is_weak_pin(pin), "{} should be accepted", pin);
+        }
+    }
+
+    #[test]
+    fn test_pin_steps_past_weak_candidates() {
+        let entropy = (0..=u8::MAX)
+            .map(|seed| [seed; ENTROPY_BYTES])
+            .find(|entropy| is_weak_pin(&pin_candidate(entropy, 0, MIN_PIN_LENGTH)))
+            .expect("some seed yields a weak first candidate");
+
+        let expected = (1..MAX_PIN_ATTEMPTS)
+            .map(|attempt| pin_candidate(&entropy, attempt, MIN_PIN_LENGTH))
+            .find(|pin| !is_weak_pin(pin))
+            .unwrap();
+        assert_eq!(render_pin(&entropy, MIN_PIN_LENGTH).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_custom_symbol_set_and_exclusions() {
+        let config = PasswordConfig {
+            symbol_set: Some("!@#$%".to_string()),
+            exclude: "aeiou".to_string(),
+            avoid_ambiguous: true,
+            ..Default::default()
+        };
+        for site in ["github.com", "example.org", "bank"] {
+            let password = generate_password("master", site, 1, &config).unwrap();
+            assert!(
+                password.chars().any(|c| "!@#$%".contains(c)),
+                "{}",
+                password
+            );
+            assert!(password
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!@#$%".contains(c)));
+            assert!(
+                !password.chars().any(|c| "aeiou0O1lI|".contains(c)),
+                "{}",
+                password
+            );
+        }
+
+        let config = PasswordConfig {
+            symbol_set: Some("!a".to_string()),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_custom_alphabet_requires_each_class() {
+        let config = PasswordConfig {
+            alphabet: Some("abcXYZ789".to_string()),
+            length: 4,
+            ..Default::default()
+        };
+        let classes = config.character_classes().unwrap();
+        assert_eq!(classes.len(), 3);
+
+        for version in [AlgorithmVersion::V1, AlgorithmVersion::V2] {
+            let config = PasswordConfig {
+                version,
+                ..config.clone()
+            };
+            let password = generate_password("master", "tv", 1, &config).unwrap();
+            assert_eq!(password.len(), 4);
+            assert!(password.chars().all(|c| "abcXYZ789".contains(c)));
+            assert!(
+                password.chars().any(|c| c.is_ascii_lowercase()),
+                "{}",
+                password
+            );
+            assert!(
+                password.chars().any(|c| c.is_ascii_uppercase()),
+                "{}",
+                password
+            );
+            assert!(password.chars().any(|c| c.is_ascii_digit()), "{}", password);
+        }
+    }
+
+    #[test]
+    fn test_class_emptied_by_exclusions() {
+        let config = PasswordConfig {
+            exclude: "0123456789".to_string(),
+            ..Default::default()
+        };
+        assert!(generate_password("master", "github.com", 1, &config).is_err());
+    }
+
+    #[test]
+    fn test_policy_constraints() {
+        let config = PasswordConfig {
+            length: 20,
+            policy: PasswordPolicy {
+                min_digits: 3,
+                min_symbols: 2,
+                max_consecutive: Some(1),
+                start_with_letter: true,
+                max_length: Some(12),
+                ..Default::default()
+            },
+            version: AlgorithmVersion::LATEST,
+            ..Default::default()
+        };
+        for site in ["github.com", "example.org", "bank", "corp"] {
+            let password: Vec<char> = generate_password("master", site, 1, &config)
+                .unwrap()
+                .chars()
+                .collect();
+            assert_eq!(password.len(), 12);
+            assert!(password[0].is_ascii_alphabetic());
+            assert!(password.iter().filter(|c| c.is_ascii_digit()).count() >= 3);
+            assert!(password.iter().filter(|c| SYMBOLS.contains(**c)).count() >= 2);
+            assert!(password.windows(2).all(|w| w[0] != w[1]));
+        }
+    }
+
+    #[test]
+    fn test_policy_retries_until_accepted() {
+        let classes = PasswordConfig::default().character_classes().unwrap();
+        let policy = PasswordPolicy {
+            start_with_letter: true,
+            ..Default::default()
+        };
+        // Find an entropy whose unconstrained rendering starts with a non-letter.
+        let entropy = (0..=u8::MAX)
+            .map(|seed| [seed; ENTROPY_BYTES])
+            .find(|entropy| {
+                let plain =
+                    render_password_v2(entropy, &classes, 16, &PasswordPolicy::default()).unwrap();
+                !plain.starts_with(|c: char| c.is_ascii_alphabetic())
+            })
+            .unwrap();
+
+        let first = render_password_v2(&entropy, &classes, 16, &policy).unwrap();
+        let second = render_password_v2(&entropy, &classes, 16, &policy).unwrap();
+        assert_eq!(first, second);
+        assert!(first.starts_with(|c: char| c.is_ascii_alphabetic()));
+    }
+
+    #[test]
+    fn test_unsatisfiable_policy() {
+        let config = PasswordConfig {
+            length: 8,
+            policy: PasswordPolicy {
+                min_digits: 5,
+                min_symbols: 5,
+                ..Default::default()
+            },
+            version: AlgorithmVersion::LATEST,
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_password("master", "github.com", 1, &config),
+            Err(EasyPasswordError::PolicyUnsatisfiable(ref message))
+                if message.starts_with("the minimums need 12 characters")
+        ));
+
+        let v1 = PasswordConfig {
+            version: AlgorithmVersion::V1,
+            policy: PasswordPolicy {
+                start_with_letter: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(
+            generate_password("master", "github.com", 1, &v1),
+            Err(EasyPasswordError::PolicyUnsatisfiable(ref message))
+                if message.contains("version 2")
+        ));
+    }
+
+    #[test]
+    fn test_template_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Template,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password("master", "helpdesk", 1, &config).unwrap(),
+            "XiyjMugaLezo5["
+        );
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Template,
+            template: "pin".to_string(),
+            ..Default::default()
+        };
+        let pin = generate_password("master", "tv", 1, &config).unwrap();
+        assert!(pin.len() == 4 && pin.bytes().all(|b| b.is_ascii_digit()));
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Template,
+            template_pattern: Some("cvc-cvc-cvc9".to_string()),
+            ..Default::default()
+        };
+        let password = generate_password("master", "tv", 1, &config).unwrap();
+        assert_eq!(password.len(), 12);
+        assert!(password.ends_with('9'));
+
+        let config = PasswordConfig {
+            mode: GenerationMode::Template,
+            template: "huge".to_string(),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_spectre_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Spectre,
+            spectre_full_name: Some("Robert Lee Mitchell".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password(
+                "banana colored duckling",
+                "masterpasswordapp.com",
+                1,
+                &config
+            )
+            .unwrap(),
+            "Jejr5[RepuSosp"
+        );
+
+        let no_name = PasswordConfig {
+            spectre_full_name: None,
+            ..config.clone()
+        };
+        assert!(no_name.validate().is_err());
+        let custom = PasswordConfig {
+            template_pattern: Some("cvc".to_string()),
+            ..config
+        };
+        assert!(custom.validate().is_err());
+    }
+
+    #[test]
+    fn test_lesspass_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::LessPass,
+            lesspass_login: "contact@example.org".to_string(),
+            length: 14,
+            use_symbols: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password("password", "example.org", 2, &config).unwrap(),
+            "MBAsB7b1Prt8Sl"
+        );
+
+        let too_long = PasswordConfig {
+            length: 36,
+            ..config
+        };
+        assert!(too_long.validate().is_err());
+    }
+
+    #[test]
+    fn test_username_mode() {
+        let config = PasswordConfig {
+            mode: GenerationMode::Username,
+            ..Default::default()
+        };
+        let username = generate_password("master", "forum.example", 1, &config).unwrap();
+        assert_eq!(username, "refrainhatchback60");
+        assert_eq!(
+            generate_password("master", "Forum.Example", 1, &config).unwrap(),
+            username
+        );
+        assert_ne!(
+            generate_password("master", "forum.example", 2, &config).unwrap(),
+            username
+        );
+    }
+
+    #[test]
+    fn test_username_salt_is_separate() {
+        let password = build_salt(Purpose::Password, "github.com", None, 1);
+        let legacy = Sha256::digest([b"github.com".as_slice(), &1u32.to_le_bytes()].concat());
+        assert_eq!(password, legacy.to_vec());
+
+        let username = build_salt(Purpose::Username, "github.com", None, 1);
+        assert_ne!(password, username);
+    }
+
+    #[test]
+    fn test_account_scoped_passwords() {
+        let config = PasswordConfig::default();
+        let plain = generate_password("master", "github.com", 1, &config).unwrap();
+        let alice_config = PasswordConfig {
+            account: Some("alice".to_string()),
+            ..Default::default()
+        };
+        let alice = generate_password("master", "github.com", 1, &alice_config).unwrap();
+        let bob_config = PasswordConfig {
+            account: Some("bob".to_string()),
+            ..Default::default()
+        };
+        let bob = generate_password("master", "github.com", 1, &bob_config).unwrap();
+        assert_ne!(alice, plain);
+        assert_ne!(alice, bob);
+
+        let upper_config = PasswordConfig {
+            account: Some("Alice".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_password("master", "github.com", 1, &upper_config).unwrap(),
+            alice
+        );
+        assert_ne!(
+            build_salt(Purpose::Username, "github.com", Some("alice"), 1),
+            build_salt(Purpose::Username, "github.com", None, 1)
+        );
+    }
+
+    #[test]
+    fn test_ssh_key_vector() {
+        // Checked with `ssh-keygen -y` and `ssh-keygen -l` on the written files.
+        let key = generate_ssh_key("master", "github", 1).unwrap();
+        assert_eq!(
+            key.public_key(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHcV0uR0M6jP1QXRjMwyOPe+kBXxpmckcmSLFnNRPiC6 github"
+        );
+        assert_eq!(
+            key.fingerprint(),
+            "SHA256:P7BE52xlk4tUm7cwDORmDV43wENC4ul9FMPZFlUy+qg"
+        );
+        assert_eq!(
+            key.passphrase(),
+            "steerable-glancing-vest-corner-dimple-preppy"
+        );
+        assert_eq!(
+            format!("{:x}", Sha256::digest(key.private_key())),
+            "c5835e6eb8d7c6b78c88ab76e986760db109b82860a25f78c46253f6c98f6a93"
+        );
+        assert_eq!(
+            format!("{:x}", Sha256::digest(key.encrypted_private_key().unwrap())),
+            "eac4804f96f06b5518c0276fb596e53ad4e8cef93b52b693fc836e11e7e7c4bf"
+        );
+
+        let next = generate_ssh_key("master", "github", 2).unwrap();
+        assert_ne!(next.public_key(), key.public_key());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let fingerprint = generate_fingerprint("master").unwrap();
+        assert_eq!(fingerprint, "pink coveting-swoosh");
+        assert_eq!(generate_fingerprint(" master\u{a0}").unwrap(), fingerprint);
+        assert_ne!(generate_fingerprint("masterr").unwrap(), fingerprint);
+    }
+
+    #[test]
+    fn test_security_answers() {
+        let config = PasswordConfig::default();
+        let pet = generate_answer("master", "bank.com", "pet", &config).unwrap();
+        assert_eq!(pet, "moneywise achiness audience");
+        assert_eq!(
+            generate_answer("master", "Bank.com", " Pet ", &config).unwrap(),
+            pet
+        );
+        assert_ne!(
+            generate_answer("master", "bank.com", "school", &config).unwrap(),
+            pet
+        );
+        assert!(generate_answer("master", "bank.com", "", &config).is_err());
+
+        let salts = [
+            build_salt(Purpose::Password, "bank.com", None, 0),
+            build_salt(Purpose::Username, "bank.com", None, 0),
+            build_salt(Purpose::Answer("pet"), "bank.com", None, 0),
+        ];
+        assert_ne!(salts[0], salts[2]);
+        assert_ne!(salts[1], salts[2]);
+    }
+
     #[test]
     fn test_concatenation_mode() {
         let config = PasswordConfig {
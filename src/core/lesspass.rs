@@ -0,0 +1,145 @@
+//! LessPass version 2, for passwords created with the LessPass apps.
+
+use hmac::Hmac;
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::error::{EasyPasswordError, Result};
+
+const ITERATIONS: u32 = 100_000;
+const ENTROPY_BYTES: usize = 32;
+pub const MIN_LENGTH: usize = 5;
+pub const MAX_LENGTH: usize = 35;
+
+/// PBKDF2-HMAC-SHA256 of the master password, salted with
+/// `site || login || counter as lowercase hex`.
+pub(super) fn entropy(master_password: &str, site: &str, login: &str, counter: u32) -> Vec<u8> {
+    let salt = format!("{}{}{:x}", site, login, counter);
+    let mut entropy = vec![0u8; ENTROPY_BYTES];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(
+        master_password.as_bytes(),
+        salt.as_bytes(),
+        ITERATIONS,
+        &mut entropy,
+    )
+    .expect("HMAC accepts keys of any length");
+    entropy
+}
+
+/// LessPass rendering: characters from the combined charset, then one
+/// character per rule inserted at pseudo-random positions, all taken from
+/// successive remainders of the entropy as a big integer.
+pub(super) fn render(entropy: &[u8], rules: &[&str], length: usize) -> Result<String> {
+    check(rules, length)?;
+
+    let mut quotient = BigUint::from_be_bytes(entropy);
+    let charset: Vec<char> = rules.concat().chars().collect();
+
+    let mut password: Vec<char> = (0..length - rules.len())
+        .map(|_| charset[quotient.div_rem(charset.len() as u32) as usize])
+        .collect();
+    let required: Vec<char> = rules
+        .iter()
+        .map(|rule| {
+            let rule = rule.as_bytes();
+            rule[quotient.div_rem(rule.len() as u32) as usize] as char
+        })
+        .collect();
+    for ch in required {
+        let pos = quotient.div_rem(password.len() as u32) as usize;
+        password.insert(pos, ch);
+    }
+
+    quotient.zeroize();
+    Ok(password.into_iter().collect())
+}
+
+/// Checks the length and rules against what LessPass accepts.
+pub(super) fn check(rules: &[&str], length: usize) -> Result<()> {
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return Err(EasyPasswordError::PasswordGeneration(format!(
+            "LessPass length must be between {} and {}",
+            MIN_LENGTH, MAX_LENGTH
+        )));
+    }
+    if rules.is_empty() {
+        return Err(EasyPasswordError::PasswordGeneration(
+            "At least one character class must be enabled".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Just enough arbitrary-precision arithmetic for LessPass: big-endian u32
+/// limbs, divided in place by small divisors.
+#[derive(Zeroize)]
+struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let mut padded = vec![0u8; (4 - bytes.len() % 4) % 4];
+        padded.extend_from_slice(bytes);
+        let limbs = padded
+            .chunks(4)
+            .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        padded.zeroize();
+        Self { limbs }
+    }
+
+    /// Divides in place and returns the remainder.
+    fn div_rem(&mut self, divisor: u32) -> u32 {
+        let mut remainder: u64 = 0;
+        for limb in self.limbs.iter_mut() {
+            let current = (remainder << 32) | u64::from(*limb);
+            *limb = (current / u64::from(divisor)) as u32;
+            remainder = current % u64::from(divisor);
+        }
+        remainder as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DIGITS, LOWERCASE, SYMBOLS, UPPERCASE};
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_lesspass_vectors() {
+        // Test vectors from the LessPass core library.
+        let first = entropy("password", "example.org", "contact@example.org", 1);
+        assert_eq!(
+            hex(&first),
+            "dc33d431bce2b01182c613382483ccdb0e2f66482cbba5e9d07dab34acc7eb1e"
+        );
+        let all = [LOWERCASE, UPPERCASE, DIGITS, SYMBOLS];
+        assert_eq!(render(&first, &all, 16).unwrap(), "WHLpUL)e00[iHR+w");
+
+        let second = entropy("password", "example.org", "contact@example.org", 2);
+        let no_symbols = [LOWERCASE, UPPERCASE, DIGITS];
+        assert_eq!(render(&second, &no_symbols, 14).unwrap(), "MBAsB7b1Prt8Sl");
+
+        let third = entropy("password", "example.org", "contact@example.org", 3);
+        assert_eq!(render(&third, &[DIGITS], 6).unwrap(), "117843");
+
+        let no_digits = [LOWERCASE, UPPERCASE, SYMBOLS];
+        assert_eq!(render(&first, &no_digits, 14).unwrap(), "sB>{qF}wN%/-fm");
+    }
+
+    #[test]
+    fn test_big_uint_div_rem() {
+        let mut n = BigUint::from_be_bytes(&[0x01, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(n.div_rem(3), 1);
+        assert_eq!(n.limbs, vec![0, 0x5555_5555]);
+        let mut small = BigUint::from_be_bytes(&[0xff]);
+        assert_eq!(small.div_rem(16), 15);
+        assert_eq!(small.div_rem(16), 15);
+        assert_eq!(small.div_rem(16), 0);
+    }
+}
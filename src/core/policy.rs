@@ -0,0 +1,168 @@
+use super::ClassKind;
+use crate::error::{EasyPasswordError, Result};
+
+/// Site password rules beyond "one character from each enabled class".
+///
+/// Minimum counts are met while rendering; the positional rules are met by
+/// deterministic retry. Requires algorithm version 2 or later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_lowercase: usize,
+    pub min_uppercase: usize,
+    pub min_digits: usize,
+    pub min_symbols: usize,
+    /// Longest allowed run of one repeated character, e.g. 2 rejects `aaa`.
+    pub max_consecutive: Option<usize>,
+    pub start_with_letter: bool,
+    /// Caps the configured length.
+    pub max_length: Option<usize>,
+}
+
+impl PasswordPolicy {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(super) fn effective_length(&self, length: usize) -> usize {
+        self.max_length.map_or(length, |max| length.min(max))
+    }
+
+    /// Characters to draw from a class: at least one, or the policy minimum.
+    pub(super) fn required_count(&self, kind: ClassKind) -> usize {
+        self.min_count(kind).max(1)
+    }
+
+    fn min_count(&self, kind: ClassKind) -> usize {
+        match kind {
+            ClassKind::Lowercase => self.min_lowercase,
+            ClassKind::Uppercase => self.min_uppercase,
+            ClassKind::Digit => self.min_digits,
+            ClassKind::Symbol => self.min_symbols,
+        }
+    }
+
+    /// Rejects policies that no password over `classes` could satisfy.
+    pub(super) fn check(&self, classes: &[Vec<char>], length: usize) -> Result<()> {
+        if self.max_consecutive == Some(0) {
+            return Err(unsatisfiable(
+                "max_consecutive must be at least 1".to_string(),
+            ));
+        }
+
+        for kind in ClassKind::ALL {
+            let enabled = classes.iter().any(|class| ClassKind::of(class[0]) == kind);
+            if self.min_count(kind) > 0 && !enabled {
+                return Err(unsatisfiable(format!(
+                    "a minimum of {} {} characters needs that class enabled",
+                    self.min_count(kind),
+                    kind.name()
+                )));
+            }
+        }
+
+        let required: usize = classes
+            .iter()
+            .map(|class| self.required_count(ClassKind::of(class[0])))
+            .sum();
+        if required > length {
+            return Err(unsatisfiable(format!(
+                "the minimums need {} characters but the length is {}",
+                required, length
+            )));
+        }
+
+        if self.start_with_letter && !classes.iter().any(|class| class[0].is_alphabetic()) {
+            return Err(unsatisfiable(
+                "start_with_letter needs a letter class enabled".to_string(),
+            ));
+        }
+
+        let distinct: usize = classes.iter().map(Vec::len).sum();
+        if let Some(max) = self.max_consecutive {
+            if distinct == 1 && length > max {
+                return Err(unsatisfiable(format!(
+                    "a single character cannot fill {} positions with runs of at most {}",
+                    length, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a rendered password follows the positional rules.
+    pub(super) fn accepts(&self, password: &[char]) -> bool {
+        if self.start_with_letter && !password.first().is_some_and(|c| c.is_alphabetic()) {
+            return false;
+        }
+        if let Some(max) = self.max_consecutive {
+            let mut run = 0;
+            for (i, ch) in password.iter().enumerate() {
+                run = if i > 0 && password[i - 1] == *ch {
+                    run + 1
+                } else {
+                    1
+                };
+                if run > max {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+fn unsatisfiable(reason: String) -> EasyPasswordError {
+    EasyPasswordError::PolicyUnsatisfiable(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_accepts_positional_rules() {
+        let policy = PasswordPolicy {
+            max_consecutive: Some(2),
+            start_with_letter: true,
+            ..Default::default()
+        };
+        assert!(policy.accepts(&chars("aa1bb2")));
+        assert!(!policy.accepts(&chars("aaa1b2")));
+        assert!(!policy.accepts(&chars("1aabb2")));
+        assert!(PasswordPolicy::default().accepts(&chars("111")));
+    }
+
+    #[test]
+    fn test_check_rejects_unsatisfiable() {
+        let classes = vec![chars("abc"), chars("123")];
+        let too_many = PasswordPolicy {
+            min_lowercase: 3,
+            min_digits: 3,
+            ..Default::default()
+        };
+        assert!(too_many.check(&classes, 6).is_ok());
+        assert!(too_many.check(&classes, 5).is_err());
+
+        let missing_class = PasswordPolicy {
+            min_symbols: 1,
+            ..Default::default()
+        };
+        assert!(missing_class.check(&classes, 8).is_err());
+
+        let no_letters = PasswordPolicy {
+            start_with_letter: true,
+            ..Default::default()
+        };
+        assert!(no_letters.check(&[chars("123")], 8).is_err());
+
+        let single_char = PasswordPolicy {
+            max_consecutive: Some(2),
+            ..Default::default()
+        };
+        assert!(single_char.check(&[chars("a")], 3).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use super::EntropyStream;
+use crate::error::{EasyPasswordError, Result};
+
+const TEMPLATE_STREAM_LABEL: &[u8] = b"easypassword/template";
+
+pub const DEFAULT_TEMPLATE: &str = "long";
+
+/// Built-in templates, with the same names and shapes as Spectre. A named
+/// template picks one of its variants per site.
+const TEMPLATES: &[(&str, &[&str])] = &[
+    ("maximum", &["anoxxxxxxxxxxxxxxxxx", "axxxxxxxxxxxxxxxxxno"]),
+    (
+        "long",
+        &[
+            "CvcvnoCvcvCvcv",
+            "CvcvCvcvnoCvcv",
+            "CvcvCvcvCvcvno",
+            "CvccnoCvcvCvcv",
+            "CvccCvcvnoCvcv",
+            "CvccCvcvCvcvno",
+            "CvcvnoCvccCvcv",
+            "CvcvCvccnoCvcv",
+            "CvcvCvccCvcvno",
+            "CvcvnoCvcvCvcc",
+            "CvcvCvcvnoCvcc",
+            "CvcvCvcvCvccno",
+            "CvccnoCvccCvcv",
+            "CvccCvccnoCvcv",
+            "CvccCvccCvcvno",
+            "CvcvnoCvccCvcc",
+            "CvcvCvccnoCvcc",
+            "CvcvCvccCvccno",
+            "CvccnoCvcvCvcc",
+            "CvccCvcvnoCvcc",
+            "CvccCvcvCvccno",
+        ],
+    ),
+    ("medium", &["CvcnoCvc", "CvcCvcno"]),
+    ("basic", &["aaanaaan", "aannaaan", "aaannaaa"]),
+    ("short", &["Cvcn"]),
+    ("pin", &["nnnn"]),
+];
+
+/// Characters for a template class letter, or `None` for a literal.
+pub(super) fn class_chars(class: char) -> Option<&'static str> {
+    match class {
+        'V' => Some("AEIOU"),
+        'C' => Some("BCDFGHJKLMNPQRSTVWXYZ"),
+        'v' => Some("aeiou"),
+        'c' => Some("bcdfghjklmnpqrstvwxyz"),
+        'A' => Some("AEIOUBCDFGHJKLMNPQRSTVWXYZ"),
+        'a' => Some("AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz"),
+        'n' => Some("0123456789"),
+        'o' => Some("@&%?,=[]_:-+*$#!'^~;()/."),
+        'x' => Some("AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz0123456789!@#$%^&*()"),
+        ' ' => Some(" "),
+        _ => None,
+    }
+}
+
+/// Variants of a built-in template.
+pub(super) fn named(name: &str) -> Result<&'static [&'static str]> {
+    TEMPLATES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, variants)| *variants)
+        .ok_or_else(|| {
+            let names: Vec<&str> = TEMPLATES.iter().map(|(n, _)| *n).collect();
+            EasyPasswordError::PasswordGeneration(format!(
+                "Unknown template '{}' (expected one of: {})",
+                name,
+                names.join(", ")
+            ))
+        })
+}
+
+/// Picks a variant, then one character per class letter; other characters
+/// are copied as they are.
+pub(super) fn render(entropy: &[u8], variants: &[&str]) -> String {
+    let mut stream = EntropyStream::new(entropy, TEMPLATE_STREAM_LABEL);
+    let template = variants[stream.next_index(variants.len())];
+
+    template
+        .chars()
+        .map(|class| match class_chars(class) {
+            Some(chars) => {
+                let chars = chars.as_bytes();
+                chars[stream.next_index(chars.len())] as char
+            }
+            None => class,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_templates_use_known_classes() {
+        for (name, variants) in TEMPLATES {
+            assert_eq!(named(name).unwrap().len(), variants.len());
+            for variant in *variants {
+                assert!(
+                    variant.chars().all(|c| class_chars(c).is_some()),
+                    "{}",
+                    name
+                );
+            }
+        }
+        assert!(named("LONG").is_ok());
+        assert!(named("huge").is_err());
+    }
+
+    #[test]
+    fn test_render_follows_template() {
+        let entropy = [42u8; 64];
+        let password = render(&entropy, &["Cvc-cvc-nn9"]);
+        let chars: Vec<char> = password.chars().collect();
+        assert_eq!(chars.len(), 11);
+        assert!("BCDFGHJKLMNPQRSTVWXYZ".contains(chars[0]));
+        assert!("aeiou".contains(chars[1]));
+        assert_eq!(chars[3], '-');
+        assert_eq!(chars[7], '-');
+        assert!(chars[8].is_ascii_digit() && chars[9].is_ascii_digit());
+        assert_eq!(chars[10], '9');
+    }
+}
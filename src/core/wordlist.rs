@@ -0,0 +1,31 @@
+use std::sync::OnceLock;
+
+/// EFF large wordlist, vendored unchanged: 7776 lines of five dice rolls, a
+/// tab and the word. Part of the passphrase algorithm; must never change.
+const WORDLIST: &str = include_str!("../../assets/eff_large_wordlist.txt");
+
+pub(crate) const WORD_COUNT: usize = 7776;
+
+pub(crate) fn words() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| {
+        WORDLIST
+            .lines()
+            .filter_map(|line| line.split_once('\t').map(|(_, word)| word))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wordlist_is_intact() {
+        let words = words();
+        assert_eq!(words.len(), WORD_COUNT);
+        assert_eq!(words[0], "abacus");
+        assert_eq!(words[WORD_COUNT - 1], "zoom");
+        assert!(words.windows(2).all(|w| w[0] < w[1]));
+    }
+}
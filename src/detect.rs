@@ -1,19 +1,85 @@
 use crossbeam_channel::Sender;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use rdev::{Event, EventType, Key};
+use zeroize::Zeroize;
 
 use crate::core::GenerationMode;
 use crate::error::Result;
+use crate::master_key::SecretKey;
+use crate::site::{QUESTION_MARKER, SCHEME_MARKER};
 
 mod platform;
 
-#[derive(Debug, Clone)]
-pub struct TriggerEvent {
-    pub site: String,
-    pub trigger_len: usize,
-    pub mode: GenerationMode,
+/// Starts an inline command right after a trigger prefix, e.g. `;;!lock`.
+const COMMAND_MARKER: char = '!';
+
+/// A master key left half-typed for this long is discarded.
+const SECRET_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An inline command typed after a trigger prefix.
+#[derive(Debug)]
+pub enum InlineCommand {
+    /// `!setkey <key>` or `!unlock <key>`, closed by the trigger prefix
+    SetKey(SecretKey),
+    /// `!lock`: forget the cached master key
+    Lock,
+    /// `!reload`
+    Reload,
+    /// `!bump <site>`: increment the site's counter
+    Bump(String),
+    /// `!pause`: ignore site triggers until `!resume`
+    Pause,
+    /// `!resume`
+    Resume,
+    /// `!status`
+    Status,
+}
+
+/// What a command name expects after it, once the name is terminated.
+enum CommandSyntax {
+    Complete(InlineCommand),
+    NeedsKey,
+    NeedsSite,
+}
+
+fn parse_command(name: &str) -> Option<CommandSyntax> {
+    match name {
+        "setkey" | "unlock" => Some(CommandSyntax::NeedsKey),
+        "bump" => Some(CommandSyntax::NeedsSite),
+        "lock" => Some(CommandSyntax::Complete(InlineCommand::Lock)),
+        "reload" => Some(CommandSyntax::Complete(InlineCommand::Reload)),
+        "pause" => Some(CommandSyntax::Complete(InlineCommand::Pause)),
+        "resume" => Some(CommandSyntax::Complete(InlineCommand::Resume)),
+        "status" => Some(CommandSyntax::Complete(InlineCommand::Status)),
+        _ => None,
+    }
+}
+
+fn command_with_site(name: &str, site: String) -> Option<InlineCommand> {
+    match name {
+        "bump" => Some(InlineCommand::Bump(site)),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum TriggerEvent {
+    Site {
+        site: String,
+        trigger_len: usize,
+        mode: GenerationMode,
+    },
+    Command {
+        command: InlineCommand,
+        trigger_len: usize,
+    },
+    /// Master key entry was abandoned with `trigger_len` characters of the
+    /// command in the field. `erasable` is false when focus or the caret may
+    /// have moved, so backspacing could delete other text.
+    KeyAbandoned { trigger_len: usize, erasable: bool },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,11 +87,17 @@ enum DetectorState {
     Idle,
     ScanningPrefix,
     CollectingSite(GenerationMode, usize), // Mode and prefix length
+    CollectingCommand(usize),              // Prefix length
+    CollectingArgument(usize, usize),      // Prefix length and argument start
+    CollectingKey(usize),                  // Prefix length
 }
 
 pub(crate) struct TriggerDetector {
     state: DetectorState,
     buffer: String,
+    secret: SecretKey,
+    /// When the last key of the master key was typed, while collecting it.
+    secret_typed_at: Option<SystemTime>,
     triggers: Vec<(String, GenerationMode)>,
     injection_active: Arc<AtomicBool>,
 }
@@ -35,11 +107,18 @@ impl TriggerDetector {
         Self {
             state: DetectorState::Idle,
             buffer: String::new(),
+            secret: SecretKey::default(),
+            secret_typed_at: None,
             triggers,
             injection_active,
         }
     }
 
+    /// True while the master key is being typed; callers must not log key events.
+    pub fn is_collecting_secret(&self) -> bool {
+        matches!(self.state, DetectorState::CollectingKey(_))
+    }
+
     pub fn process_event(&mut self, event: &Event) -> Option<TriggerEvent> {
         if self.injection_active.load(Ordering::SeqCst) {
             log::debug!("[SKIP] Injection active, ignoring event");
@@ -47,6 +126,15 @@ impl TriggerDetector {
         }
 
         match &event.event_type {
+            EventType::ButtonPress(_) if self.is_collecting_secret() => {
+                // A click may move focus; the typed key could no longer be erased.
+                log::info!("[SECRET] Click during master key entry, discarding");
+                self.abandon_secret(false)
+            }
+            EventType::KeyPress(key) if self.is_collecting_secret() => {
+                log::debug!("[KEY] Press: <redacted> | state: {:?}", self.state);
+                self.handle_secret_key(*key, event)
+            }
             EventType::KeyPress(key) => {
                 log::debug!(
                     "[KEY] Press: {:?} | name: {:?} | state: {:?} | buffer: \"{}\"",
@@ -55,16 +143,106 @@ impl TriggerDetector {
                     self.state,
                     self.buffer
                 );
-                self.handle_key_press(*key, event)
+                let trigger = self.handle_key_press(*key, event);
+                if self.is_collecting_secret() {
+                    self.secret_typed_at = Some(event.time);
+                }
+                trigger
             }
             EventType::KeyRelease(key) => {
-                log::debug!("[KEY] Release: {:?}", key);
+                if !self.is_collecting_secret() {
+                    log::debug!("[KEY] Release: {:?}", key);
+                }
                 None
             }
             _ => None,
         }
     }
 
+    fn secret_timed_out(&self, now: SystemTime) -> bool {
+        self.secret_typed_at.is_some_and(|typed_at| {
+            now.duration_since(typed_at)
+                .is_ok_and(|idle| idle > SECRET_IDLE_TIMEOUT)
+        })
+    }
+
+    /// Every character up to the closing trigger prefix belongs to the master
+    /// key, spaces included, so the field only ever receives characters and
+    /// nothing submits it before the key is erased. Keys that could submit the
+    /// field or move the caret or focus, Return among them, abandon the command.
+    fn handle_secret_key(&mut self, key: Key, event: &Event) -> Option<TriggerEvent> {
+        if is_modifier(key) {
+            return None;
+        }
+        let timed_out = self.secret_timed_out(event.time);
+        self.secret_typed_at = Some(event.time);
+
+        match key {
+            Key::Backspace => self.handle_backspace(),
+            Key::Space => self.secret.push(' '),
+            _ => {
+                let ch = match &event.name {
+                    Some(name) if !name.is_empty() => name.chars().next(),
+                    _ => key_to_char(key),
+                };
+                match ch {
+                    Some(ch) if !ch.is_control() => self.secret.push(ch),
+                    _ => {
+                        log::info!(
+                            "[SECRET] Non-character key during master key entry, discarding"
+                        );
+                        return self.abandon_secret(false);
+                    }
+                }
+            }
+        }
+
+        if !self.is_collecting_secret() {
+            // Backspaced past the start of the key.
+            return None;
+        }
+        if timed_out {
+            // The caret has not moved, so this key landed after the rest.
+            log::info!("[SECRET] Master key entry timed out, discarding");
+            return self.abandon_secret(true);
+        }
+        self.finish_secret()
+    }
+
+    /// Ends the master key once the trigger prefix that started the command is
+    /// typed again, as in `;;!setkey <key>;;`.
+    fn finish_secret(&mut self) -> Option<TriggerEvent> {
+        let DetectorState::CollectingKey(prefix_len) = self.state else {
+            return None;
+        };
+        let prefix = &self.buffer[..prefix_len];
+        if !self.secret.expose().ends_with(prefix) {
+            return None;
+        }
+        let prefix_chars = prefix.chars().count();
+        let typed_len = self.secret.char_count();
+        if typed_len == prefix_chars {
+            log::info!("[SECRET] Empty master key, discarding");
+            return self.abandon_secret(true);
+        }
+        for _ in 0..prefix_chars {
+            self.secret.pop();
+        }
+        let secret = std::mem::take(&mut self.secret);
+        self.finish_command(InlineCommand::SetKey(secret), typed_len)
+    }
+
+    /// Discards a half-typed master key, reporting how much of the command is
+    /// left in the field.
+    fn abandon_secret(&mut self, erasable: bool) -> Option<TriggerEvent> {
+        let trigger_len = self.buffer.chars().count() + self.secret.char_count();
+        self.reset();
+        Some(TriggerEvent::KeyAbandoned {
+            trigger_len,
+            erasable,
+        })
+    }
+
     fn handle_key_press(&mut self, key: Key, event: &Event) -> Option<TriggerEvent> {
         if key == Key::Backspace {
             self.handle_backspace();
@@ -74,7 +252,7 @@ impl TriggerDetector {
 
         if is_terminator(key) {
             log::debug!("[TERMINATOR] {:?} pressed, checking trigger...", key);
-            return self.handle_terminator();
+            return self.handle_terminator(key);
         }
 
         let ch = match &event.name {
@@ -136,8 +314,12 @@ impl TriggerDetector {
                 }
                 None
             }
-            DetectorState::CollectingSite(_mode, _) => {
-                if is_valid_site_char(ch) {
+            DetectorState::CollectingSite(_mode, prefix_len) => {
+                if ch == COMMAND_MARKER && self.buffer.len() == prefix_len {
+                    self.buffer.push(ch);
+                    self.state = DetectorState::CollectingCommand(prefix_len);
+                    log::debug!("[STATE] CollectingSite -> CollectingCommand");
+                } else if is_valid_site_char(ch) {
                     self.buffer.push(ch);
                     log::debug!("[COLLECT] buffer: \"{}\"", self.buffer);
                 } else {
@@ -149,6 +331,30 @@ impl TriggerDetector {
                 }
                 None
             }
+            DetectorState::CollectingCommand(_) => {
+                if ch.is_ascii_alphabetic() {
+                    self.buffer.push(ch);
+                    log::debug!("[COMMAND] buffer: \"{}\"", self.buffer);
+                } else {
+                    log::debug!(
+                        "[STATE] CollectingCommand -> Idle (invalid char: '{}') | resetting",
+                        ch
+                    );
+                    self.reset();
+                }
+                None
+            }
+            DetectorState::CollectingArgument(_, _) => {
+                if is_valid_site_char(ch) {
+                    self.buffer.push(ch);
+                    log::debug!("[ARGUMENT] buffer: \"{}\"", self.buffer);
+                } else {
+                    self.reset();
+                }
+                None
+            }
+            // Handled by `handle_secret_key`.
+            DetectorState::CollectingKey(_) => None,
         }
     }
 
@@ -168,41 +374,113 @@ impl TriggerDetector {
         })
     }
 
-    fn handle_terminator(&mut self) -> Option<TriggerEvent> {
-        if let DetectorState::CollectingSite(mode, prefix_len) = self.state {
-            if self.buffer.len() > prefix_len {
-                let site = self.buffer[prefix_len..].to_string();
-                let trigger_len = self.buffer.chars().count() + 1;
-                log::info!(
-                    "[TRIGGER] site: \"{}\" | len: {} | mode: {:?} | buffer: \"{}\"",
-                    site,
-                    trigger_len,
-                    mode,
+    fn handle_terminator(&mut self, key: Key) -> Option<TriggerEvent> {
+        match self.state {
+            DetectorState::CollectingSite(mode, prefix_len) if self.buffer.len() > prefix_len => {
+                self.finish_site(mode, prefix_len)
+            }
+            DetectorState::CollectingCommand(prefix_len) => {
+                let name = &self.buffer[prefix_len + COMMAND_MARKER.len_utf8()..];
+                match parse_command(name) {
+                    Some(CommandSyntax::Complete(command)) => self.finish_command(command, 1),
+                    Some(CommandSyntax::NeedsKey) if key == Key::Space => {
+                        self.buffer.push(' ');
+                        self.state = DetectorState::CollectingKey(prefix_len);
+                        log::debug!("[STATE] CollectingCommand -> CollectingKey");
+                        None
+                    }
+                    Some(CommandSyntax::NeedsSite) if key == Key::Space => {
+                        self.buffer.push(' ');
+                        self.state =
+                            DetectorState::CollectingArgument(prefix_len, self.buffer.len());
+                        log::debug!("[STATE] CollectingCommand -> CollectingArgument");
+                        None
+                    }
+                    Some(_) => {
+                        log::debug!("[COMMAND] Incomplete command: \"{}\"", name);
+                        self.reset();
+                        None
+                    }
+                    None => {
+                        // A mistyped command must not turn into a site; what
+                        // follows could be the master key.
+                        log::info!("[COMMAND] Unknown command \"{}\", ignoring", name);
+                        self.reset();
+                        None
+                    }
+                }
+            }
+            DetectorState::CollectingArgument(prefix_len, arg_start)
+                if self.buffer.len() > arg_start =>
+            {
+                let name = &self.buffer[prefix_len + COMMAND_MARKER.len_utf8()..arg_start - 1];
+                let site = self.buffer[arg_start..].to_string();
+                match command_with_site(name, site) {
+                    Some(command) => self.finish_command(command, 1),
+                    None => {
+                        self.reset();
+                        None
+                    }
+                }
+            }
+            _ => {
+                log::debug!(
+                    "[TERMINATOR] No trigger (state: {:?}, buffer: \"{}\")",
+                    self.state,
                     self.buffer
                 );
                 self.reset();
-                return Some(TriggerEvent {
-                    site,
-                    trigger_len,
-                    mode,
-                });
+                None
             }
         }
-        log::debug!(
-            "[TERMINATOR] No trigger (state: {:?}, buffer: \"{}\")",
-            self.state,
+    }
+
+    fn finish_site(&mut self, mode: GenerationMode, prefix_len: usize) -> Option<TriggerEvent> {
+        let site = self.buffer[prefix_len..].to_string();
+        let trigger_len = self.buffer.chars().count() + 1;
+        log::info!(
+            "[TRIGGER] site: \"{}\" | len: {} | mode: {:?} | buffer: \"{}\"",
+            site,
+            trigger_len,
+            mode,
             self.buffer
         );
         self.reset();
-        None
+        Some(TriggerEvent::Site {
+            site,
+            trigger_len,
+            mode,
+        })
+    }
+
+    /// `tail_len` counts typed chars that are not in `buffer`: the terminator,
+    /// or the master key and the prefix closing it.
+    fn finish_command(&mut self, command: InlineCommand, tail_len: usize) -> Option<TriggerEvent> {
+        let trigger_len = self.buffer.chars().count() + tail_len;
+        log::info!("[COMMAND] {:?} | len: {}", command, trigger_len);
+        self.reset();
+        Some(TriggerEvent::Command {
+            command,
+            trigger_len,
+        })
     }
 
     fn reset(&mut self) {
         self.state = DetectorState::Idle;
         self.buffer.clear();
+        self.secret.zeroize();
+        self.secret_typed_at = None;
     }
 
     fn handle_backspace(&mut self) {
+        if self.is_collecting_secret() {
+            // Backspacing past the start of the key abandons the command.
+            if self.secret.pop().is_none() {
+                self.reset();
+            }
+            return;
+        }
+
         if !self.buffer.is_empty() && self.state != DetectorState::Idle {
             self.buffer.pop();
 
@@ -213,14 +491,24 @@ impl TriggerDetector {
             }
 
             // Check if we are still collecting site or moved back to prefix scanning
-            if let DetectorState::CollectingSite(_, prefix_len) = self.state {
-                if self.buffer.len() < prefix_len {
-                    // We backspaced into the prefix.
-                    // For simplicity, just reset to avoid complex state transitions backwards.
+            match self.state {
+                // We backspaced into the prefix.
+                // For simplicity, just reset to avoid complex state transitions backwards.
+                DetectorState::CollectingSite(_, prefix_len) if self.buffer.len() < prefix_len => {
                     self.reset();
                 }
-            } else if self.state == DetectorState::ScanningPrefix && !self.check_prefixes() {
-                self.reset();
+                DetectorState::CollectingCommand(prefix_len) if self.buffer.len() <= prefix_len => {
+                    self.reset();
+                }
+                DetectorState::CollectingArgument(_, arg_start)
+                    if self.buffer.len() < arg_start =>
+                {
+                    self.reset();
+                }
+                DetectorState::ScanningPrefix if !self.check_prefixes() => {
+                    self.reset();
+                }
+                _ => {}
             }
         }
     }
@@ -274,8 +562,10 @@ mod tests {
         }
 
         let t = found_trigger.expect("Should have detected trigger");
-        assert_eq!(t.site, "site");
-        assert_eq!(t.mode, GenerationMode::Argon2id);
+        assert!(matches!(
+            t,
+            TriggerEvent::Site { ref site, mode: GenerationMode::Argon2id, .. } if site == "site"
+        ));
 
         let events = vec![
             EventType::KeyPress(Key::Num1),
@@ -305,13 +595,283 @@ mod tests {
         }
 
         let t = found_trigger.expect("Should have detected concat trigger");
-        assert_eq!(t.site, "a");
-        assert_eq!(t.mode, GenerationMode::Concatenation);
+        assert!(matches!(
+            t,
+            TriggerEvent::Site { ref site, mode: GenerationMode::Concatenation, .. } if site == "a"
+        ));
+    }
+
+    fn type_text(detector: &mut TriggerDetector, text: &str) -> Option<TriggerEvent> {
+        let mut found = None;
+        for ch in text.chars() {
+            let key = match ch {
+                ' ' => Key::Space,
+                '\n' => Key::Return,
+                '\t' => Key::Tab,
+                _ => Key::KeyA,
+            };
+            let event = Event {
+                time: std::time::SystemTime::now(),
+                name: Some(ch.to_string()),
+                event_type: EventType::KeyPress(key),
+            };
+            if let Some(t) = detector.process_event(&event) {
+                found = Some(t);
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn test_setkey_command() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        assert!(type_text(&mut detector, ";;!setkey ").is_none());
+        assert!(detector.is_collecting_secret());
+
+        assert!(type_text(&mut detector, "s3cr#t key ").is_none());
+        assert!(type_text(&mut detector, "horse;").is_none());
+        // The closing prefix is a character like any other: the trigger fires
+        // on it, covering everything typed, before any key that could submit
+        // the field.
+        let t = type_text(&mut detector, ";").expect("Should have detected setkey");
+        let TriggerEvent::Command {
+            command: InlineCommand::SetKey(key),
+            trigger_len,
+        } = t
+        else {
+            panic!("expected SetKey command");
+        };
+        assert_eq!(key.expose(), "s3cr#t key horse");
+        assert_eq!(trigger_len, ";;!setkey s3cr#t key horse;;".len());
+        assert!(!format!("{:?}", key).contains("s3cr#t"));
+        assert!(!detector.is_collecting_secret());
+
+        // Return would reach the app first, so it never ends the key.
+        let t = type_text(&mut detector, ";;!setkey s3cr#t\n").expect("Should have abandoned");
+        assert!(matches!(
+            t,
+            TriggerEvent::KeyAbandoned {
+                trigger_len: 16,
+                erasable: false
+            }
+        ));
+        assert!(!detector.is_collecting_secret());
+    }
+
+    #[test]
+    fn test_setkey_abandoned() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        let t = type_text(&mut detector, ";;!setkey par\t").expect("Should have abandoned");
+        assert!(matches!(
+            t,
+            TriggerEvent::KeyAbandoned {
+                trigger_len: 13,
+                erasable: false
+            }
+        ));
+        assert!(!detector.is_collecting_secret());
+        assert!(type_text(&mut detector, "tial\n").is_none());
+
+        type_text(&mut detector, ";;!setkey part");
+        let t = detector
+            .process_event(&Event {
+                time: std::time::SystemTime::now(),
+                name: None,
+                event_type: EventType::ButtonPress(rdev::Button::Left),
+            })
+            .expect("Should have abandoned");
+        assert!(matches!(
+            t,
+            TriggerEvent::KeyAbandoned {
+                erasable: false,
+                ..
+            }
+        ));
+        assert!(!detector.is_collecting_secret());
+
+        // After a timeout the caret is where it was, so the late key is
+        // erased along with the rest.
+        type_text(&mut detector, ";;!setkey part");
+        let late = Event {
+            time: std::time::SystemTime::now() + SECRET_IDLE_TIMEOUT * 2,
+            name: Some("x".to_string()),
+            event_type: EventType::KeyPress(Key::KeyX),
+        };
+        let t = detector
+            .process_event(&late)
+            .expect("Should have abandoned");
+        assert!(matches!(
+            t,
+            TriggerEvent::KeyAbandoned {
+                trigger_len: 15,
+                erasable: true
+            }
+        ));
+        assert!(!detector.is_collecting_secret());
+        assert!(detector.secret.is_empty());
+
+        let t = type_text(&mut detector, ";;!setkey ;;").expect("Should have abandoned");
+        assert!(matches!(
+            t,
+            TriggerEvent::KeyAbandoned {
+                trigger_len: 12,
+                erasable: true
+            }
+        ));
+    }
+
+    #[test]
+    fn test_setkey_backspace_into_command_resets() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        type_text(&mut detector, ";;!setkey ab");
+        let backspace = Event {
+            time: std::time::SystemTime::now(),
+            name: None,
+            event_type: EventType::KeyPress(Key::Backspace),
+        };
+        for _ in 0..3 {
+            detector.process_event(&backspace);
+        }
+        assert!(!detector.is_collecting_secret());
+        assert!(type_text(&mut detector, "key ").is_none());
+    }
+
+    #[test]
+    fn test_inline_commands() {
+        let triggers = vec![
+            (";;".to_string(), GenerationMode::Argon2id),
+            ("!!".to_string(), GenerationMode::Concatenation),
+        ];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        let t = type_text(&mut detector, ";;!lock ").expect("Should have detected lock");
+        assert!(matches!(
+            t,
+            TriggerEvent::Command {
+                command: InlineCommand::Lock,
+                trigger_len: 8
+            }
+        ));
+
+        let t = type_text(&mut detector, "!!!reload\n").expect("Should have detected reload");
+        assert!(matches!(
+            t,
+            TriggerEvent::Command {
+                command: InlineCommand::Reload,
+                ..
+            }
+        ));
+
+        type_text(&mut detector, ";;!lock");
+        let keypad_enter = Event {
+            time: std::time::SystemTime::now(),
+            name: None,
+            event_type: EventType::KeyPress(Key::KpReturn),
+        };
+        let t = detector
+            .process_event(&keypad_enter)
+            .expect("Should have detected lock");
+        assert!(matches!(
+            t,
+            TriggerEvent::Command {
+                command: InlineCommand::Lock,
+                trigger_len: 8
+            }
+        ));
+
+        assert!(type_text(&mut detector, ";;!bump ").is_none());
+        let t = type_text(&mut detector, "github.com ").expect("Should have detected bump");
+        assert!(matches!(
+            t,
+            TriggerEvent::Command {
+                command: InlineCommand::Bump(ref site),
+                trigger_len: 19
+            } if site == "github.com"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_and_incomplete_commands() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        assert!(type_text(&mut detector, ";;!bump\n").is_none());
+        assert!(type_text(&mut detector, ";;!lo,ck ").is_none());
+
+        // Unknown or mistyped command names never become sites.
+        assert!(type_text(&mut detector, ";;!frobnicate ").is_none());
+        assert!(type_text(&mut detector, ";;!lo.ck ").is_none());
+        assert!(type_text(&mut detector, ";;!setky hunter2 ").is_none());
+        assert!(!detector.is_collecting_secret());
+
+        // A '!' inside a site name is still part of the site.
+        let t = type_text(&mut detector, ";;site!2 ").expect("Should have detected trigger");
+        assert!(matches!(t, TriggerEvent::Site { ref site, .. } if site == "site!2"));
+    }
+
+    #[test]
+    fn test_security_question_site() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        let t = type_text(&mut detector, ";;bank.com?pet ").expect("Should have detected trigger");
+        assert!(matches!(
+            t,
+            TriggerEvent::Site { ref site, trigger_len: 15, .. } if site == "bank.com?pet"
+        ));
+    }
+
+    #[test]
+    fn test_otp_site() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        let t = type_text(&mut detector, ";;totp:github ").expect("Should have detected trigger");
+        assert!(matches!(
+            t,
+            TriggerEvent::Site { ref site, trigger_len: 14, .. } if site == "totp:github"
+        ));
+    }
+
+    #[test]
+    fn test_url_site() {
+        let triggers = vec![(";;".to_string(), GenerationMode::Argon2id)];
+        let injection = Arc::new(AtomicBool::new(false));
+        let mut detector = TriggerDetector::new(triggers, injection);
+
+        let t = type_text(&mut detector, ";;https://github.com/login ")
+            .expect("Should have detected trigger");
+        assert!(matches!(
+            t,
+            TriggerEvent::Site { ref site, .. } if site == "https://github.com/login"
+        ));
     }
 }
 
-fn is_valid_site_char(ch: char) -> bool {
-    ch.is_ascii_alphanumeric() || ch == '.' || ch == '-' || ch == '_' || ch == '!' || ch == '@'
+/// Whether `ch` can be part of a site or name typed after a trigger prefix.
+pub fn is_valid_site_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric()
+        || ch == '.'
+        || ch == '-'
+        || ch == '_'
+        || ch == '!'
+        || ch == '@'
+        || ch == '/'
+        || ch == QUESTION_MARKER
+        || ch == SCHEME_MARKER
 }
 
 fn key_to_char(key: Key) -> Option<char> {
@@ -368,7 +928,22 @@ fn key_to_char(key: Key) -> Option<char> {
 }
 
 fn is_terminator(key: Key) -> bool {
-    matches!(key, Key::Space | Key::Return | Key::Tab)
+    matches!(key, Key::Space | Key::Return | Key::KpReturn | Key::Tab)
+}
+
+fn is_modifier(key: Key) -> bool {
+    matches!(
+        key,
+        Key::ShiftLeft
+            | Key::ShiftRight
+            | Key::ControlLeft
+            | Key::ControlRight
+            | Key::Alt
+            | Key::AltGr
+            | Key::MetaLeft
+            | Key::MetaRight
+            | Key::CapsLock
+    )
 }
 
 pub fn start_keyboard_listener(
@@ -111,10 +111,18 @@ fn keycode_to_key(keycode: u16) -> Option<rdev::Key> {
         49 => Some(rdev::Key::Space),
         50 => Some(rdev::Key::BackQuote),
         51 => Some(rdev::Key::Backspace),
+        76 => Some(rdev::Key::KpReturn),
         _ => None,
     }
 }
 
+fn send_trigger(tx: &Sender<TriggerEvent>, trigger: TriggerEvent) {
+    log::info!("[SEND-MACOS] Sending trigger event: {:?}", trigger);
+    if let Err(e) = tx.send(trigger) {
+        log::error!("[ERROR-MACOS] Failed to send trigger: {}", e);
+    }
+}
+
 pub fn start_keyboard_listener(
     tx: Sender<TriggerEvent>,
     triggers: Vec<(String, GenerationMode)>,
@@ -142,7 +150,11 @@ pub fn start_keyboard_listener(
             CGEventTapLocation::Session,
             CGEventTapPlacement::HeadInsertEventTap,
             CGEventTapOptions::ListenOnly,
-            vec![CGEventType::KeyDown],
+            vec![
+                CGEventType::KeyDown,
+                CGEventType::LeftMouseDown,
+                CGEventType::RightMouseDown,
+            ],
             move |_proxy, event_type, event: &CGEvent| {
                 if matches!(
                     event_type,
@@ -168,6 +180,25 @@ pub fn start_keyboard_listener(
                     return None;
                 }
 
+                if matches!(
+                    event_type,
+                    CGEventType::LeftMouseDown | CGEventType::RightMouseDown
+                ) {
+                    let button = match event_type {
+                        CGEventType::RightMouseDown => rdev::Button::Right,
+                        _ => rdev::Button::Left,
+                    };
+                    let click = rdev::Event {
+                        time: std::time::SystemTime::now(),
+                        name: None,
+                        event_type: rdev::EventType::ButtonPress(button),
+                    };
+                    if let Some(trigger) = detector.borrow_mut().process_event(&click) {
+                        send_trigger(&tx_clone, trigger);
+                    }
+                    return None;
+                }
+
                 let is_autorepeat =
                     event.get_integer_value_field(EventField::KEYBOARD_EVENT_AUTOREPEAT) != 0;
                 if is_autorepeat {
@@ -177,31 +208,35 @@ pub fn start_keyboard_listener(
                 let keycode =
                     event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE) as u16;
                 let flags = event.get_flags();
+                let collecting_secret = detector.borrow().is_collecting_secret();
+                let shortcut = flags.contains(CGEventFlags::CGEventFlagCommand)
+                    || flags.contains(CGEventFlags::CGEventFlagControl);
 
-                if flags.contains(CGEventFlags::CGEventFlagCommand)
-                    || flags.contains(CGEventFlags::CGEventFlagControl)
+                if (shortcut && !collecting_secret)
                     || flags.contains(CGEventFlags::CGEventFlagAlternate)
                 {
                     return None;
                 }
 
-                if let Some(key) = keycode_to_key(keycode) {
+                let (key, name) = match keycode_to_key(keycode) {
+                    Some(key) if !shortcut => (key, event_unicode_name(event)),
+                    // Shortcuts and keys such as arrows may move the caret or
+                    // focus; the detector abandons the master key on them.
+                    _ if collecting_secret => (rdev::Key::Unknown(keycode.into()), None),
+                    _ => return None,
+                };
+                if !collecting_secret {
                     log::debug!("[MACOS-KEY] keycode={} -> {:?}", keycode, key);
+                }
 
-                    let name = event_unicode_name(event);
-
-                    let rdev_event = rdev::Event {
-                        time: std::time::SystemTime::now(),
-                        name,
-                        event_type: rdev::EventType::KeyPress(key),
-                    };
+                let rdev_event = rdev::Event {
+                    time: std::time::SystemTime::now(),
+                    name,
+                    event_type: rdev::EventType::KeyPress(key),
+                };
 
-                    if let Some(trigger) = detector.borrow_mut().process_event(&rdev_event) {
-                        log::info!("[SEND-MACOS] Sending trigger event: {:?}", trigger);
-                        if let Err(e) = tx_clone.send(trigger) {
-                            log::error!("[ERROR-MACOS] Failed to send trigger: {}", e);
-                        }
-                    }
+                if let Some(trigger) = detector.borrow_mut().process_event(&rdev_event) {
+                    send_trigger(&tx_clone, trigger);
                 }
 
                 None
@@ -2,12 +2,21 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum EasyPasswordError {
-    #[error("Master key not set. Use ;;!setkey<space>your_master_key to set it.")]
+    #[error("Master key not set. Use ;;!setkey<space>your_master_key;; to set it.")]
     MasterKeyNotSet,
 
+    #[error("Master key fingerprint is \"{actual}\" but \"{expected}\" was expected; the key may be mistyped")]
+    FingerprintMismatch { expected: String, actual: String },
+
     #[error("Failed to generate password: {0}")]
     PasswordGeneration(String),
 
+    #[error("Password policy cannot be satisfied: {0}")]
+    PolicyUnsatisfiable(String),
+
+    #[error("One-time code error: {0}")]
+    Otp(String),
+
     #[error("Keyboard monitoring error: {0}")]
     KeyboardMonitor(String),
 
@@ -0,0 +1,87 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use easypassword::{generate_ssh_key, Config};
+use zeroize::Zeroizing;
+
+use crate::read_master_key;
+
+pub fn run(label: &str, output: Option<PathBuf>, encrypt: bool, force: bool) -> anyhow::Result<()> {
+    let private_path = match output {
+        Some(path) => path,
+        None => default_path(label)?,
+    };
+
+    let config = Config::load()?;
+    let (master_key, fingerprint) = read_master_key(&config)?;
+
+    let counter = config.get_counter(label);
+    let key = generate_ssh_key(&master_key, label, counter)?;
+
+    let mut public_path = private_path.clone().into_os_string();
+    public_path.push(".pub");
+    let public_path = PathBuf::from(public_path);
+
+    for path in [&private_path, &public_path] {
+        if path.exists() && !force {
+            bail!(
+                "{} already exists; pass --force to overwrite",
+                path.display()
+            );
+        }
+    }
+
+    let private_key = Zeroizing::new(if encrypt {
+        key.encrypted_private_key()?
+    } else {
+        key.private_key()
+    });
+    write_private(&private_path, &private_key)?;
+    fs::write(&public_path, format!("{}\n", key.public_key()))
+        .with_context(|| format!("failed to write {}", public_path.display()))?;
+
+    println!(
+        "Wrote {} and {}",
+        private_path.display(),
+        public_path.display()
+    );
+    println!("Key fingerprint: {}", key.fingerprint());
+    println!("Master key fingerprint: {}", fingerprint);
+    if encrypt {
+        println!("Passphrase: {}", key.passphrase());
+    }
+    Ok(())
+}
+
+/// `id_ed25519_<label>` in the current directory. Labels that could name a
+/// file elsewhere, such as `../github` or `a/b`, need an explicit `--output`.
+fn default_path(label: &str) -> anyhow::Result<PathBuf> {
+    if !label
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+    {
+        bail!(
+            "label {:?} cannot be used in a file name; pass --output to choose the key file",
+            label
+        );
+    }
+    Ok(PathBuf::from(format!("id_ed25519_{}", label)))
+}
+
+/// Writes the private key readable by the owner only, as `ssh` requires.
+fn write_private(path: &Path, contents: &str) -> anyhow::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
@@ -4,10 +4,18 @@ pub mod detect;
 pub mod error;
 pub mod inject;
 pub mod master_key;
+pub mod otp;
+pub mod site;
+pub mod ssh;
 
 pub use config::Config;
-pub use core::{generate_password, PasswordConfig};
-pub use detect::{start_keyboard_listener, TriggerEvent};
+pub use core::{
+    generate_answer, generate_fingerprint, generate_otp_key, generate_password, generate_ssh_key,
+    PasswordConfig,
+};
+pub use detect::{start_keyboard_listener, InlineCommand, TriggerEvent};
 pub use error::{EasyPasswordError, Result};
 pub use inject::TextInjector;
-pub use master_key::MasterKeyCache;
+pub use master_key::{MasterKeyCache, SecretKey};
+pub use site::SiteInput;
+pub use ssh::SshKey;
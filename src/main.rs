@@ -9,28 +9,59 @@ use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossbeam_channel::{select, unbounded, Receiver, Sender};
 
 use std::process::Command;
 
 use easypassword::core::GenerationMode;
+use easypassword::otp::OtpKind;
+use easypassword::ssh::agent::{KeySpec, SshAgent};
 use easypassword::{
-    generate_password, start_keyboard_listener, Config, TextInjector, TriggerEvent,
+    generate_answer, generate_fingerprint, generate_otp_key, generate_password,
+    start_keyboard_listener, Config, EasyPasswordError, InlineCommand, MasterKeyCache, SecretKey,
+    SiteInput, TextInjector, TriggerEvent,
 };
+use zeroize::{Zeroize, Zeroizing};
 
 mod autostart;
+mod bench;
+mod keygen;
+mod otp_import;
+mod policy;
 mod tray;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum ControlCommand {
     ReloadConfig,
     OpenConfig,
     SetAutostart(bool),
+    SetMasterKey(SecretKey),
+    Lock,
+    BumpCounter(String),
+    /// Moves an HOTP entry past the code just typed.
+    AdvanceHotp(String),
+    SetPaused(bool),
+    ShowStatus,
     Exit,
 }
 
+impl From<InlineCommand> for ControlCommand {
+    fn from(command: InlineCommand) -> Self {
+        match command {
+            InlineCommand::SetKey(key) => Self::SetMasterKey(key),
+            InlineCommand::Lock => Self::Lock,
+            InlineCommand::Reload => Self::ReloadConfig,
+            InlineCommand::Bump(site) => Self::BumpCounter(site),
+            InlineCommand::Pause => Self::SetPaused(true),
+            InlineCommand::Resume => Self::SetPaused(false),
+            InlineCommand::Status => Self::ShowStatus,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TrayUpdate {
     AutostartSetResult {
@@ -38,6 +69,13 @@ pub enum TrayUpdate {
         ok: bool,
         error: Option<String>,
     },
+    /// The cached master key changed; `rejected` means it failed `expected_fingerprint`.
+    MasterKey {
+        fingerprint: Option<String>,
+        rejected: bool,
+    },
+    /// `!status` output, shown as the tray tooltip.
+    Status(String),
 }
 
 #[derive(Parser)]
@@ -47,6 +85,76 @@ pub enum TrayUpdate {
 struct Cli {
     #[arg(short, long)]
     verbose: bool,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Time Argon2id costs on this machine and recommend the strongest that fit a target
+    Bench {
+        /// Target time per password in milliseconds
+        #[arg(long, default_value_t = 300)]
+        target_ms: u64,
+
+        /// Argon2id parallelism (lanes) to benchmark
+        #[arg(long, default_value_t = 1)]
+        parallelism: u32,
+    },
+    /// Derive an Ed25519 SSH key from the master key and write it in OpenSSH format
+    SshKey {
+        /// Label the key is derived from, e.g. `github` or `work-laptop`
+        label: String,
+
+        /// Private key file; the public key is written next to it with `.pub` appended
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Encrypt the private key with a passphrase derived from the master key
+        #[arg(long)]
+        encrypt: bool,
+
+        /// Overwrite existing key files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show the master key's fingerprint, to check that it was typed correctly
+    Fingerprint,
+    /// Store a 2FA secret from an otpauth:// URI, encrypted under the master key
+    OtpImport {
+        /// Name to type after `totp:`; defaults to the URI's issuer or account
+        name: Option<String>,
+
+        /// Replace an existing entry with the same name
+        #[arg(long)]
+        force: bool,
+    },
+    /// Print the effective settings for a site after groups, rules and aliases
+    Policy {
+        /// Site as typed after the trigger, e.g. `chase.com` or `alice@github.com`
+        site: String,
+    },
+}
+
+/// The master key from the config file, or typed at a hidden prompt, along
+/// with its fingerprint. Fails if the fingerprint is not the expected one.
+pub(crate) fn read_master_key(config: &Config) -> anyhow::Result<(Zeroizing<String>, String)> {
+    let key = match config
+        .default
+        .master_key
+        .as_deref()
+        .filter(|k| !k.is_empty())
+    {
+        Some(key) => Zeroizing::new(key.to_string()),
+        None => Zeroizing::new(rpassword::prompt_password("Master key: ")?),
+    };
+    if key.is_empty() {
+        anyhow::bail!("master key must not be empty");
+    }
+    let fingerprint = generate_fingerprint(&key)?;
+    config.check_fingerprint(&fingerprint)?;
+    Ok((key, fingerprint))
 }
 
 fn log_path() -> Option<PathBuf> {
@@ -77,45 +185,262 @@ fn init_logging(verbose: bool) {
 
 fn handle_trigger(
     config: &Config,
-    master_key: Option<&str>,
+    master_key: &MasterKeyCache,
+    paused: bool,
     injector: &mut TextInjector,
+    command_tx: &Sender<ControlCommand>,
+    tray_update_tx: &Sender<TrayUpdate>,
     trigger: TriggerEvent,
 ) {
     log::info!("[HANDLE] Received trigger: {:?}", trigger);
 
-    let mut password_config = config.get_password_config(&trigger.site);
+    match trigger {
+        TriggerEvent::Site {
+            site,
+            trigger_len,
+            mode,
+        } => {
+            if paused {
+                log::info!("[HANDLE] Detection paused, ignoring site={}", site);
+                return;
+            }
+            let input = config.parse_site(&site);
+            if input.otp {
+                handle_otp_trigger(
+                    config,
+                    master_key,
+                    injector,
+                    command_tx,
+                    input.site,
+                    trigger_len,
+                );
+            } else {
+                handle_site_trigger(config, master_key, injector, input, trigger_len, mode);
+            }
+        }
+        TriggerEvent::Command {
+            command,
+            trigger_len,
+        } => {
+            if let Err(e) = injector.clear_text(trigger_len) {
+                log::error!("failed to erase inline command: {}", e);
+            }
+            let _ = command_tx.send(ControlCommand::from(command));
+        }
+        TriggerEvent::KeyAbandoned {
+            trigger_len,
+            erasable,
+        } => {
+            let erased = erasable
+                && match injector.clear_text(trigger_len) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::error!("failed to erase abandoned master key: {}", e);
+                        false
+                    }
+                };
+            let warning = if erased {
+                "master key entry abandoned; the typed key was erased"
+            } else {
+                "master key entry abandoned; erase the partly typed key from the field"
+            };
+            log::warn!("{}", warning);
+            let _ = tray_update_tx.send(TrayUpdate::Status(format!("EasyPassword: {}", warning)));
+        }
+    }
+}
 
-    if trigger.mode == GenerationMode::Concatenation {
-        password_config.mode = GenerationMode::Concatenation;
+fn handle_site_trigger(
+    config: &Config,
+    master_key: &MasterKeyCache,
+    injector: &mut TextInjector,
+    input: SiteInput,
+    trigger_len: usize,
+    mode: GenerationMode,
+) {
+    let site = &*config.canonical_site(input.site);
+    if site != input.site {
+        log::info!("[HANDLE] {} is an alias of {}", input.site, site);
     }
+    let mut password_config = config.get_account_password_config(site, input.account);
 
-    let counter = config.get_counter(&trigger.site);
+    // Dedicated prefixes choose the mode; the main prefix keeps the site's configured mode.
+    if mode != GenerationMode::Argon2id {
+        password_config.mode = mode;
+    }
 
-    let Some(master_key) = master_key else {
-        log::error!(
-            "master_key not set; cannot generate password (site={})",
-            trigger.site
-        );
+    let counter = config.get_account_counter(site, input.account);
+
+    let Some(mut key) = master_key.get() else {
+        log::error!("{} (site={})", EasyPasswordError::MasterKeyNotSet, site);
         return;
     };
 
-    log::info!("[HANDLE] Generating password for site={}", trigger.site);
+    let result = match input.question {
+        Some(question) => {
+            log::info!(
+                "[HANDLE] Generating answer for site={} question={}",
+                site,
+                question
+            );
+            generate_answer(&key, site, question, &password_config)
+        }
+        None => {
+            log::info!("[HANDLE] Generating password for site={}", site);
+            generate_password(&key, site, counter, &password_config)
+        }
+    };
+    key.zeroize();
 
-    match generate_password(master_key, &trigger.site, counter, &password_config) {
+    match result {
         Ok(password) => {
             log::info!("[HANDLE] Password generated, injecting...");
-            if let Err(e) = injector.replace_trigger(trigger.trigger_len, &password) {
-                log::error!("injection failed (site={}): {}", trigger.site, e);
+            if let Err(e) = injector.replace_trigger(trigger_len, &password) {
+                log::error!("injection failed (site={}): {}", site, e);
             } else {
                 log::info!("[HANDLE] Injection successful");
             }
         }
         Err(e) => {
-            log::error!("generation failed (site={}): {}", trigger.site, e);
+            log::error!("generation failed (site={}): {}", site, e);
         }
     }
 }
 
+fn handle_otp_trigger(
+    config: &Config,
+    master_key: &MasterKeyCache,
+    injector: &mut TextInjector,
+    command_tx: &Sender<ControlCommand>,
+    name: &str,
+    trigger_len: usize,
+) {
+    let Some(entry) = config.otp_entry(name) else {
+        log::error!("no OTP entry named {}", name);
+        return;
+    };
+    let Some(mut key) = master_key.get() else {
+        log::error!("{} (otp={})", EasyPasswordError::MasterKeyNotSet, name);
+        return;
+    };
+
+    log::info!("[HANDLE] Generating one-time code for {}", name);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let result = generate_otp_key(&key).and_then(|otp_key| entry.code(&otp_key, name, now));
+    key.zeroize();
+
+    match result {
+        Ok(code) => {
+            if let Err(e) = injector.replace_trigger(trigger_len, &code) {
+                log::error!("injection failed (otp={}): {}", name, e);
+                return;
+            }
+            if entry.kind == OtpKind::Hotp {
+                let _ = command_tx.send(ControlCommand::AdvanceHotp(name.to_string()));
+            }
+        }
+        Err(e) => log::error!("one-time code failed (otp={}): {}", name, e),
+    }
+}
+
+/// Seeds the cache from `master_key` in the config file, if one is set there.
+/// A key entered in-band with `;;!setkey` is kept when the config has none,
+/// but is checked again in case `expected_fingerprint` changed.
+fn load_config_master_key(
+    config: &Config,
+    master_key: &MasterKeyCache,
+    tray_update_tx: &Sender<TrayUpdate>,
+) -> Option<String> {
+    let key = config
+        .default
+        .master_key
+        .as_deref()
+        .filter(|k| !k.is_empty())
+        .map(str::to_string)
+        .or_else(|| master_key.get())?;
+    set_master_key(config, master_key, SecretKey::new(key), tray_update_tx)
+}
+
+/// Caches `key` if its fingerprint matches `expected_fingerprint`, and
+/// forgets any cached key otherwise. Returns the fingerprint of the key set.
+fn set_master_key(
+    config: &Config,
+    master_key: &MasterKeyCache,
+    key: SecretKey,
+    tray_update_tx: &Sender<TrayUpdate>,
+) -> Option<String> {
+    let result = generate_fingerprint(key.expose()).and_then(|fingerprint| {
+        config.check_fingerprint(&fingerprint)?;
+        Ok(fingerprint)
+    });
+    let fingerprint = match result {
+        Ok(fingerprint) => {
+            master_key.set_secret(key);
+            log::info!("master key set, fingerprint: {}", fingerprint);
+            Some(fingerprint)
+        }
+        Err(e) => {
+            master_key.clear();
+            log::error!("master key rejected: {}", e);
+            None
+        }
+    };
+    let _ = tray_update_tx.send(TrayUpdate::MasterKey {
+        fingerprint: fingerprint.clone(),
+        rejected: fingerprint.is_none(),
+    });
+    fingerprint
+}
+
+fn ssh_agent_keys(config: &Config) -> Vec<KeySpec> {
+    config
+        .default
+        .ssh_agent_keys
+        .iter()
+        .map(|label| KeySpec {
+            label: label.clone(),
+            counter: config.get_counter(label),
+        })
+        .collect()
+}
+
+/// Hands the configured keys to the agent, starting its socket the first time
+/// any are configured.
+fn update_ssh_agent(config: &Config, agent: &SshAgent, serving: &mut bool) {
+    agent.set_keys(ssh_agent_keys(config));
+    if *serving || config.default.ssh_agent_keys.is_empty() {
+        return;
+    }
+    let result = config
+        .ssh_agent_socket_path()
+        .and_then(|path| agent.serve(&path).map(|()| path));
+    match result {
+        Ok(path) => {
+            *serving = true;
+            log::info!("ssh-agent listening on {}", path.display());
+        }
+        Err(e) => log::error!("failed to start ssh-agent: {}", e),
+    }
+}
+
+fn config_error_status(error: &str) -> String {
+    format!("EasyPassword: config error, fix it and reload: {}", error)
+}
+
+fn status_line(fingerprint: Option<&str>, paused: bool) -> String {
+    format!(
+        "EasyPassword {}: master key {}, detection {}",
+        env!("CARGO_PKG_VERSION"),
+        match fingerprint {
+            Some(fingerprint) => format!("set ({})", fingerprint),
+            None => "not set".to_string(),
+        },
+        if paused { "paused" } else { "active" }
+    )
+}
+
 fn open_config_file() -> anyhow::Result<()> {
     let path = Config::config_path()?;
 
@@ -148,27 +473,36 @@ fn open_config_file() -> anyhow::Result<()> {
 fn worker_loop(
     trigger_tx: Sender<TriggerEvent>,
     trigger_rx: Receiver<TriggerEvent>,
+    command_tx: Sender<ControlCommand>,
     command_rx: Receiver<ControlCommand>,
     tray_update_tx: Sender<TrayUpdate>,
 ) {
-    let mut config = match Config::load() {
-        Ok(c) => c,
+    // Without a valid config, the defaults only supply trigger prefixes: site
+    // triggers are refused and nothing is saved until a reload succeeds.
+    let (mut config, mut config_error) = match Config::load() {
+        Ok(c) => (c, None),
         Err(e) => {
             log::error!("failed to load config: {}", e);
-            Config::default()
+            (Config::default(), Some(e.to_string()))
         }
     };
 
-    if let Err(e) = autostart::set_enabled(config.default.autostart) {
-        log::error!("failed to apply autostart setting: {}", e);
+    if config_error.is_none() {
+        if let Err(e) = autostart::set_enabled(config.default.autostart) {
+            log::error!("failed to apply autostart setting: {}", e);
+        }
     }
 
-    let mut master_key = config
-        .default
-        .master_key
-        .as_deref()
-        .filter(|k| !k.is_empty())
-        .map(str::to_string);
+    let master_key = MasterKeyCache::new();
+    let mut fingerprint = load_config_master_key(&config, &master_key, &tray_update_tx);
+    if let Some(error) = &config_error {
+        let _ = tray_update_tx.send(TrayUpdate::Status(config_error_status(error)));
+    }
+    let mut paused = false;
+
+    let ssh_agent = SshAgent::new(master_key.clone(), Vec::new());
+    let mut ssh_agent_serving = false;
+    update_ssh_agent(&config, &ssh_agent, &mut ssh_agent_serving);
 
     let injection_active = Arc::new(AtomicBool::new(false));
 
@@ -181,6 +515,14 @@ fn worker_loop(
             config.default.concat_trigger_prefix.clone(),
             GenerationMode::Concatenation,
         ),
+        (
+            config.default.passphrase_trigger_prefix.clone(),
+            GenerationMode::Passphrase,
+        ),
+        (
+            config.default.username_trigger_prefix.clone(),
+            GenerationMode::Username,
+        ),
     ];
 
     if let Err(e) = start_keyboard_listener(trigger_tx, triggers, injection_active.clone()) {
@@ -200,8 +542,22 @@ fn worker_loop(
         select! {
             recv(trigger_rx) -> msg => {
                 match msg {
+                    Ok(TriggerEvent::Site { site, .. }) if config_error.is_some() => {
+                        // The defaults derive other passwords than the user's config.
+                        log::error!("config failed to load, ignoring site={}", site);
+                        let error = config_error.as_deref().unwrap_or_default();
+                        let _ = tray_update_tx.send(TrayUpdate::Status(config_error_status(error)));
+                    }
                     Ok(trigger) => {
-                        handle_trigger(&config, master_key.as_deref(), &mut injector, trigger);
+                        handle_trigger(
+                            &config,
+                            &master_key,
+                            paused,
+                            &mut injector,
+                            &command_tx,
+                            &tray_update_tx,
+                            trigger,
+                        );
                     }
                     Err(e) => {
                         log::error!("trigger channel closed: {}", e);
@@ -216,12 +572,10 @@ fn worker_loop(
                         match Config::load() {
                             Ok(c) => {
                                 config = c;
-                                master_key = config
-                                    .default
-                                    .master_key
-                                    .as_deref()
-                                    .filter(|k| !k.is_empty())
-                                    .map(str::to_string);
+                                config_error = None;
+                                fingerprint =
+                                    load_config_master_key(&config, &master_key, &tray_update_tx);
+                                update_ssh_agent(&config, &ssh_agent, &mut ssh_agent_serving);
 
                                 let requested_autostart = config.default.autostart;
                                 match autostart::set_enabled(requested_autostart) {
@@ -249,6 +603,12 @@ fn worker_loop(
                             }
                             Err(e) => {
                                 log::error!("failed to reload config: {}", e);
+                                if config_error.is_some() {
+                                    let error = e.to_string();
+                                    let _ = tray_update_tx
+                                        .send(TrayUpdate::Status(config_error_status(&error)));
+                                    config_error = Some(error);
+                                }
                             }
                         }
                     }
@@ -257,6 +617,15 @@ fn worker_loop(
                             log::error!("failed to open config file: {}", e);
                         }
                     }
+                    Ok(ControlCommand::SetAutostart(enabled)) if config_error.is_some() => {
+                        // Saving now would replace the user's config with the defaults.
+                        let _ = tray_update_tx.send(TrayUpdate::AutostartSetResult {
+                            enabled: !enabled,
+                            ok: false,
+                            error: config_error.clone(),
+                        });
+                        log::error!("config failed to load, not changing autostart");
+                    }
                     Ok(ControlCommand::SetAutostart(enabled)) => {
                         let previous = config.default.autostart;
 
@@ -287,6 +656,50 @@ fn worker_loop(
                             }
                         }
                     }
+                    Ok(ControlCommand::SetMasterKey(key)) => {
+                        fingerprint = set_master_key(&config, &master_key, key, &tray_update_tx);
+                        ssh_agent.forget_keys();
+                    }
+                    Ok(ControlCommand::Lock) => {
+                        master_key.clear();
+                        fingerprint = None;
+                        ssh_agent.forget_keys();
+                        let _ = tray_update_tx.send(TrayUpdate::MasterKey {
+                            fingerprint: None,
+                            rejected: false,
+                        });
+                        log::info!("master key cleared");
+                    }
+                    Ok(ControlCommand::BumpCounter(site)) if config_error.is_some() => {
+                        log::error!("config failed to load, not bumping the counter for {}", site);
+                    }
+                    Ok(ControlCommand::BumpCounter(site)) => {
+                        let input = config.parse_site(&site);
+                        let counter = config.bump_account_counter(input.site, input.account);
+                        ssh_agent.set_keys(ssh_agent_keys(&config));
+                        match config.save() {
+                            Ok(()) => log::info!("counter for {} bumped to {}", site, counter),
+                            Err(e) => log::error!("failed to save bumped counter: {}", e),
+                        }
+                    }
+                    Ok(ControlCommand::AdvanceHotp(name)) => {
+                        if let Some(counter) = config.advance_hotp_counter(&name) {
+                            match config.save() {
+                                Ok(()) => log::info!("HOTP counter for {} moved to {}", name, counter),
+                                Err(e) => log::error!("failed to save HOTP counter: {}", e),
+                            }
+                        }
+                    }
+                    Ok(ControlCommand::SetPaused(value)) => {
+                        paused = value;
+                        log::info!("detection {}", if paused { "paused" } else { "resumed" });
+                    }
+                    Ok(ControlCommand::ShowStatus) => {
+                        // Never typed: the focused field could be a chat box or a terminal.
+                        let status = status_line(fingerprint.as_deref(), paused);
+                        log::info!("{}", status);
+                        let _ = tray_update_tx.send(TrayUpdate::Status(status));
+                    }
                     Ok(ControlCommand::Exit) => {
                         log::info!("exit requested");
                         std::process::exit(0);
@@ -301,8 +714,30 @@ fn worker_loop(
     }
 }
 
-fn run(cli: Cli) -> anyhow::Result<()> {
-    init_logging(cli.verbose);
+fn run_command(command: Commands) -> anyhow::Result<()> {
+    match command {
+        Commands::Bench {
+            target_ms,
+            parallelism,
+        } => bench::run(target_ms, parallelism),
+        Commands::SshKey {
+            label,
+            output,
+            encrypt,
+            force,
+        } => keygen::run(&label, output, encrypt, force),
+        Commands::OtpImport { name, force } => otp_import::run(name, force),
+        Commands::Policy { site } => policy::run(&site),
+        Commands::Fingerprint => {
+            let (_, fingerprint) = read_master_key(&Config::load()?)?;
+            println!("{}", fingerprint);
+            Ok(())
+        }
+    }
+}
+
+fn run(verbose: bool) -> anyhow::Result<()> {
+    init_logging(verbose);
 
     let (trigger_tx, trigger_rx) = unbounded::<TriggerEvent>();
     let (command_tx, command_rx) = unbounded::<ControlCommand>();
@@ -311,8 +746,15 @@ fn run(cli: Cli) -> anyhow::Result<()> {
     let initial_autostart = Config::load().map(|c| c.default.autostart).unwrap_or(false);
 
     let worker_trigger_tx = trigger_tx.clone();
+    let worker_command_tx = command_tx.clone();
     let _worker = thread::spawn(move || {
-        worker_loop(worker_trigger_tx, trigger_rx, command_rx, tray_update_tx)
+        worker_loop(
+            worker_trigger_tx,
+            trigger_rx,
+            worker_command_tx,
+            command_rx,
+            tray_update_tx,
+        )
     });
 
     tray::run_tray(command_tx, tray_update_rx, initial_autostart)
@@ -321,7 +763,15 @@ fn run(cli: Cli) -> anyhow::Result<()> {
 fn main() {
     let cli = Cli::parse();
 
-    if let Err(e) = run(cli) {
+    if let Some(command) = cli.command {
+        if let Err(e) = run_command(command) {
+            eprintln!("error: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(e) = run(cli.verbose) {
         log::error!("fatal error: {}", e);
         std::process::exit(1);
     }
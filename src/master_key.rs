@@ -1,11 +1,46 @@
+use std::fmt;
 use std::sync::{Arc, RwLock};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+/// A master key held in memory. Wiped on drop and never printed by `Debug`.
+/// Not `Clone`, so it is moved rather than copied where zeroize cannot reach.
 #[derive(Default, Zeroize, ZeroizeOnDrop)]
-struct SecretKey {
+pub struct SecretKey {
     key: String,
 }
 
+impl SecretKey {
+    pub fn new(key: String) -> Self {
+        Self { key }
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.key
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key.is_empty()
+    }
+
+    pub(crate) fn push(&mut self, ch: char) {
+        self.key.push(ch);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<char> {
+        self.key.pop()
+    }
+
+    pub(crate) fn char_count(&self) -> usize {
+        self.key.chars().count()
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
 #[derive(Clone)]
 pub struct MasterKeyCache {
     inner: Arc<RwLock<Option<SecretKey>>>,
@@ -25,8 +60,12 @@ impl MasterKeyCache {
     }
 
     pub fn set(&self, key: String) {
+        self.set_secret(SecretKey::new(key));
+    }
+
+    pub fn set_secret(&self, key: SecretKey) {
         let mut guard = self.inner.write().unwrap();
-        *guard = Some(SecretKey { key });
+        *guard = Some(key);
     }
 
     pub fn get(&self) -> Option<String> {
@@ -0,0 +1,422 @@
+//! One-time codes (HOTP, RFC 4226, and TOTP, RFC 6238) from secrets kept
+//! encrypted in the config under a key derived from the master key.
+
+use base64ct::{Base64, Encoding};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+use zeroize::Zeroizing;
+
+use crate::error::{EasyPasswordError, Result};
+
+/// Types the code for an entry instead of a password, e.g. `totp:github`.
+pub const OTP_PREFIX: &str = "totp:";
+
+/// Version tag of sealed secrets in the config.
+const SEALED_PREFIX: &str = "v1:";
+const NONCE_BYTES: usize = 12;
+
+pub const MIN_DIGITS: u32 = 6;
+pub const MAX_DIGITS: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtpKind {
+    /// Time-based: a new code every `period` seconds.
+    #[default]
+    Totp,
+    /// Counter-based: the counter moves on after each code.
+    Hotp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OtpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// An `[otp.<name>]` entry in the config.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OtpEntry {
+    /// The shared secret, sealed with `seal`; never stored in plaintext.
+    pub secret: String,
+    #[serde(default)]
+    pub kind: OtpKind,
+    #[serde(default)]
+    pub algorithm: OtpAlgorithm,
+    #[serde(default = "default_digits")]
+    pub digits: u32,
+    #[serde(default = "default_period")]
+    pub period: u64,
+    /// Next HOTP counter.
+    #[serde(default)]
+    pub counter: u64,
+    #[serde(default)]
+    pub issuer: Option<String>,
+}
+
+fn default_digits() -> u32 {
+    6
+}
+
+fn default_period() -> u64 {
+    30
+}
+
+impl OtpEntry {
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_DIGITS..=MAX_DIGITS).contains(&self.digits) {
+            return Err(EasyPasswordError::Otp(format!(
+                "Digits must be between {} and {}",
+                MIN_DIGITS, MAX_DIGITS
+            )));
+        }
+        if self.period == 0 {
+            return Err(EasyPasswordError::Otp(
+                "Period must be at least 1 second".to_string(),
+            ));
+        }
+        if !self.secret.starts_with(SEALED_PREFIX) {
+            return Err(EasyPasswordError::Otp(
+                "Secret is not encrypted; import it with `easypassword otp-import`".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The code for `unix_time` (TOTP) or for the current counter (HOTP).
+    pub fn code(&self, key: &[u8; 32], name: &str, unix_time: u64) -> Result<String> {
+        let secret = open(key, name, &self.secret)?;
+        Ok(match self.kind {
+            OtpKind::Totp => totp(&secret, unix_time, self.period, self.digits, self.algorithm),
+            OtpKind::Hotp => hotp(&secret, self.counter, self.digits, self.algorithm),
+        })
+    }
+}
+
+/// HOTP: HMAC of the big-endian counter, dynamically truncated to `digits`.
+pub fn hotp(secret: &[u8], counter: u64, digits: u32, algorithm: OtpAlgorithm) -> String {
+    let message = counter.to_be_bytes();
+    let hash = match algorithm {
+        OtpAlgorithm::Sha1 => mac::<Hmac<Sha1>>(secret, &message),
+        OtpAlgorithm::Sha256 => mac::<Hmac<Sha256>>(secret, &message),
+        OtpAlgorithm::Sha512 => mac::<Hmac<Sha512>>(secret, &message),
+    };
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(digits),
+        width = digits as usize
+    )
+}
+
+/// TOTP: HOTP with the number of `period`-second steps since the epoch.
+pub fn totp(
+    secret: &[u8],
+    unix_time: u64,
+    period: u64,
+    digits: u32,
+    algorithm: OtpAlgorithm,
+) -> String {
+    hotp(secret, unix_time / period, digits, algorithm)
+}
+
+fn mac<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Zeroizing<Vec<u8>> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    Zeroizing::new(mac.finalize().into_bytes().to_vec())
+}
+
+/// Encrypts a secret for the entry `name` with ChaCha20-Poly1305. The name is
+/// authenticated, so a sealed secret cannot be moved to another entry. The
+/// nonce is a MAC of the name and secret, so sealing is deterministic and
+/// needs no random source.
+pub fn seal(key: &[u8; 32], name: &str, secret: &[u8]) -> String {
+    let name = name.to_lowercase();
+    let nonce_input = [name.as_bytes(), &[0], secret].concat();
+    let tag = mac::<Hmac<Sha256>>(key, &nonce_input);
+    let nonce = Nonce::from_slice(&tag[..NONCE_BYTES]);
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(
+            nonce,
+            Payload {
+                msg: secret,
+                aad: name.as_bytes(),
+            },
+        )
+        .expect("ChaCha20-Poly1305 encrypts any length that fits in memory");
+
+    let sealed = [nonce.as_slice(), &ciphertext].concat();
+    let mut encoded = vec![0u8; Base64::encoded_len(&sealed)];
+    let encoded = Base64::encode(&sealed, &mut encoded).expect("buffer sized by encoded_len");
+    format!("{}{}", SEALED_PREFIX, encoded)
+}
+
+/// Decrypts a secret sealed by `seal` for the entry `name`.
+pub fn open(key: &[u8; 32], name: &str, sealed: &str) -> Result<Zeroizing<Vec<u8>>> {
+    let invalid = || EasyPasswordError::Otp(format!("Secret for {} is invalid", name));
+    let encoded = sealed.strip_prefix(SEALED_PREFIX).ok_or_else(invalid)?;
+    let mut buf = vec![0u8; encoded.len()];
+    let sealed = Base64::decode(encoded, &mut buf).map_err(|_| invalid())?;
+    if sealed.len() < NONCE_BYTES {
+        return Err(invalid());
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_BYTES);
+    let name = name.to_lowercase();
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: name.as_bytes(),
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| {
+            EasyPasswordError::Otp(format!(
+                "Secret for {} cannot be decrypted; was it imported with another master key?",
+                name
+            ))
+        })
+}
+
+/// A parsed `otpauth://` URI, as shown in 2FA QR codes.
+#[derive(Debug, Clone)]
+pub struct OtpUri {
+    pub kind: OtpKind,
+    pub issuer: Option<String>,
+    pub account: String,
+    pub secret: Zeroizing<Vec<u8>>,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub counter: u64,
+}
+
+impl OtpUri {
+    /// Parses `otpauth://TYPE/[ISSUER:]ACCOUNT?secret=BASE32&...`.
+    pub fn parse(uri: &str) -> Result<Self> {
+        let invalid =
+            |reason: &str| EasyPasswordError::Otp(format!("Invalid otpauth URI: {}", reason));
+        let rest = uri
+            .trim()
+            .strip_prefix("otpauth://")
+            .ok_or_else(|| invalid("must start with otpauth://"))?;
+        let (kind, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| invalid("missing label"))?;
+        let kind = match kind.to_ascii_lowercase().as_str() {
+            "totp" => OtpKind::Totp,
+            "hotp" => OtpKind::Hotp,
+            _ => return Err(invalid("type must be totp or hotp")),
+        };
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let label = percent_decode(label).ok_or_else(|| invalid("bad label encoding"))?;
+        let (mut issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.to_string()), account.trim().to_string()),
+            None => (None, label.clone()),
+        };
+
+        let mut parsed = Self {
+            kind,
+            issuer: None,
+            account,
+            secret: Zeroizing::new(Vec::new()),
+            algorithm: OtpAlgorithm::Sha1,
+            digits: default_digits(),
+            period: default_period(),
+            counter: 0,
+        };
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value).ok_or_else(|| invalid("bad parameter encoding"))?;
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => {
+                    parsed.secret =
+                        base32_decode(&value).ok_or_else(|| invalid("secret is not base32"))?;
+                }
+                "issuer" => issuer = Some(value),
+                "algorithm" => {
+                    parsed.algorithm = match value.to_ascii_uppercase().as_str() {
+                        "SHA1" => OtpAlgorithm::Sha1,
+                        "SHA256" => OtpAlgorithm::Sha256,
+                        "SHA512" => OtpAlgorithm::Sha512,
+                        _ => return Err(invalid("unsupported algorithm")),
+                    }
+                }
+                "digits" => parsed.digits = value.parse().map_err(|_| invalid("bad digits"))?,
+                "period" => parsed.period = value.parse().map_err(|_| invalid("bad period"))?,
+                "counter" => parsed.counter = value.parse().map_err(|_| invalid("bad counter"))?,
+                _ => {}
+            }
+        }
+        if parsed.secret.is_empty() {
+            return Err(invalid("missing secret"));
+        }
+        parsed.issuer = issuer.filter(|i| !i.is_empty());
+        Ok(parsed)
+    }
+
+    /// A config entry holding this URI's secret, sealed for `name`.
+    pub fn to_entry(&self, key: &[u8; 32], name: &str) -> OtpEntry {
+        OtpEntry {
+            secret: seal(key, name, &self.secret),
+            kind: self.kind,
+            algorithm: self.algorithm,
+            digits: self.digits,
+            period: self.period,
+            counter: self.counter,
+            issuer: self.issuer.clone(),
+        }
+    }
+}
+
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = input.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// RFC 4648 base32, ignoring case, spaces and padding as authenticator apps do.
+fn base32_decode(input: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let mut decoded = Zeroizing::new(Vec::new());
+    let mut buffer: u64 = 0;
+    let mut bits = 0;
+    for ch in input.chars().filter(|c| !c.is_whitespace() && *c != '=') {
+        let value = match ch.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u64 - 'A' as u64,
+            c @ '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotp_rfc4226_vectors() {
+        // RFC 4226, appendix D.
+        let secret = b"12345678901234567890";
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(secret, counter as u64, 6, OtpAlgorithm::Sha1), *code);
+        }
+    }
+
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        // RFC 6238, appendix B: 8 digits, 30-second steps.
+        let sha1 = b"12345678901234567890".as_slice();
+        let sha256 = b"12345678901234567890123456789012".as_slice();
+        let sha512 = b"1234567890123456789012345678901234567890123456789012345678901234".as_slice();
+        let vectors: [(u64, &str, &str, &str); 6] = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, code1, code256, code512) in vectors {
+            assert_eq!(totp(sha1, time, 30, 8, OtpAlgorithm::Sha1), code1);
+            assert_eq!(totp(sha256, time, 30, 8, OtpAlgorithm::Sha256), code256);
+            assert_eq!(totp(sha512, time, 30, 8, OtpAlgorithm::Sha512), code512);
+        }
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = [7u8; 32];
+        let sealed = seal(&key, "GitHub", b"secret");
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("secret"));
+        assert_eq!(open(&key, "github", &sealed).unwrap().as_slice(), b"secret");
+        assert!(open(&[8u8; 32], "github", &sealed).is_err());
+        assert!(open(&key, "gitlab", &sealed).is_err());
+        assert!(open(&key, "github", "v1:AAAA").is_err());
+    }
+
+    #[test]
+    fn test_parse_otpauth_uri() {
+        let uri = OtpUri::parse(
+            "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+        assert_eq!(uri.kind, OtpKind::Totp);
+        assert_eq!(uri.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(uri.account, "john.doe@email.com");
+        assert_eq!(uri.secret.len(), 20);
+        assert_eq!(uri.algorithm, OtpAlgorithm::Sha256);
+        assert_eq!((uri.digits, uri.period), (8, 60));
+
+        let hotp = OtpUri::parse("otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQ&counter=5").unwrap();
+        assert_eq!(hotp.kind, OtpKind::Hotp);
+        assert_eq!(hotp.secret.as_slice(), b"1234567890");
+        assert_eq!(hotp.counter, 5);
+        assert_eq!(hotp.issuer, None);
+
+        assert!(OtpUri::parse("otpauth://totp/alice").is_err());
+        assert!(OtpUri::parse("otpauth://totp/alice?secret=189").is_err());
+        assert!(OtpUri::parse("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_entry_code() {
+        let key = [1u8; 32];
+        let uri =
+            OtpUri::parse("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&digits=8")
+                .unwrap();
+        let entry = uri.to_entry(&key, "x");
+        assert!(entry.validate().is_ok());
+        assert_eq!(entry.code(&key, "x", 59).unwrap(), "94287082");
+
+        let plaintext = OtpEntry {
+            secret: "GEZDGNBVGY3TQOJQ".to_string(),
+            ..entry
+        };
+        assert!(plaintext.validate().is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::bail;
+use easypassword::detect::is_valid_site_char;
+use easypassword::otp::OtpUri;
+use easypassword::{generate_otp_key, Config};
+use zeroize::Zeroizing;
+
+use crate::read_master_key;
+
+pub fn run(name: Option<String>, force: bool) -> anyhow::Result<()> {
+    let mut config = Config::load()?;
+
+    // Read at a hidden prompt so the secret stays out of shell history.
+    let uri = Zeroizing::new(rpassword::prompt_password("otpauth:// URI: ")?);
+    let uri = OtpUri::parse(&uri)?;
+    let name = match name {
+        Some(name) => name.to_lowercase(),
+        None => slugify(uri.issuer.as_deref().unwrap_or(&uri.account)),
+    };
+    if name.is_empty() || !name.chars().all(is_valid_site_char) {
+        bail!(
+            "{:?} cannot be typed after totp:; pass a name of letters, digits and `.-_`, e.g. `easypassword otp-import github`",
+            name
+        );
+    }
+    if config.otp_entry(&name).is_some() && !force {
+        bail!(
+            "an OTP entry named {} exists; pass --force to replace it",
+            name
+        );
+    }
+
+    let (master_key, fingerprint) = read_master_key(&config)?;
+    let key = generate_otp_key(&master_key)?;
+    let entry = uri.to_entry(&key, &name);
+    entry.validate()?;
+    config.otp.insert(name.clone(), entry);
+    config.save()?;
+
+    println!("Master key fingerprint: {}", fingerprint);
+    println!(
+        "Saved OTP entry {}; type ;;totp:{} to enter its code",
+        name, name
+    );
+    Ok(())
+}
+
+/// Turns an issuer such as `Google Cloud` into a name that can be typed after
+/// `totp:`, e.g. `google-cloud`.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for ch in text.to_lowercase().chars() {
+        if is_valid_site_char(ch) {
+            slug.push(ch);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
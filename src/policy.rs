@@ -0,0 +1,78 @@
+use easypassword::config::SiteConfig;
+use easypassword::core::PasswordConfig;
+use easypassword::Config;
+
+/// Prints the settings a site resolves to, in the config file's own keys, so
+/// any line can be copied into a `[sites]` table.
+pub fn run(site: &str) -> anyhow::Result<()> {
+    let config = Config::load()?;
+    let input = config.parse_site(site);
+    let canonical = config.canonical_site(input.site);
+    let password_config = config.get_account_password_config(&canonical, input.account);
+    let counter = config.get_account_counter(&canonical, input.account);
+    let group = config.site_group(&canonical, input.account);
+
+    if canonical != input.site {
+        println!("# {} is an alias of {}", input.site, canonical);
+    }
+    println!(
+        "# derived from site {:?}",
+        password_config.site_name(&canonical)
+    );
+    let tables = config.matched_tables(&canonical, input.account);
+    if tables.is_empty() {
+        println!("# no matching tables; [default] applies");
+    } else {
+        println!("# [default] < {}", tables.join(" < "));
+    }
+    print!("{}", settings(&password_config, counter, group)?);
+    if let Err(e) = password_config.validate() {
+        println!("# warning: {}", e);
+    }
+    Ok(())
+}
+
+/// Serializes the resolved settings as a site table, so strings are quoted
+/// and escaped the way TOML expects.
+fn settings(c: &PasswordConfig, counter: u32, group: Option<String>) -> anyhow::Result<String> {
+    let table = SiteConfig {
+        length: Some(c.length),
+        lowercase: Some(c.use_lowercase),
+        uppercase: Some(c.use_uppercase),
+        digits: Some(c.use_digits),
+        symbols: Some(c.use_symbols),
+        symbol_set: c.symbol_set.clone(),
+        exclude: Some(c.exclude.clone()),
+        avoid_ambiguous: Some(c.avoid_ambiguous),
+        alphabet: c.alphabet.clone(),
+        min_lowercase: Some(c.policy.min_lowercase),
+        min_uppercase: Some(c.policy.min_uppercase),
+        min_digits: Some(c.policy.min_digits),
+        min_symbols: Some(c.policy.min_symbols),
+        max_consecutive: c.policy.max_consecutive,
+        start_with_letter: Some(c.policy.start_with_letter),
+        max_length: c.policy.max_length,
+        counter: Some(counter),
+        mode: Some(c.mode),
+        version: Some(c.version),
+        argon2_m_cost: Some(c.argon2.m_cost),
+        argon2_t_cost: Some(c.argon2.t_cost),
+        argon2_p_cost: Some(c.argon2.p_cost),
+        passphrase_words: Some(c.passphrase.words),
+        passphrase_separator: Some(c.passphrase.separator.clone()),
+        passphrase_capitalize: Some(c.passphrase.capitalize),
+        pin_length: Some(c.pin_length),
+        username_words: Some(c.username.words),
+        username_digits: Some(c.username.digits),
+        answer_words: Some(c.answer_words),
+        template: Some(c.template.clone()),
+        template_pattern: c.template_pattern.clone(),
+        spectre_full_name: c.spectre_full_name.clone(),
+        lesspass_login: Some(c.lesspass_login.clone()),
+        normalize: Some(c.normalize_site),
+        group,
+        account: c.account.clone(),
+        ..Default::default()
+    };
+    Ok(toml::to_string(&table)?)
+}
@@ -0,0 +1,194 @@
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use publicsuffix::{List, Psl};
+
+use crate::otp::OTP_PREFIX;
+
+/// Snapshot of the Public Suffix List (publicsuffix.org), used to find the
+/// registrable domain of a host. Updating it can change which part of a
+/// host counts as the site, so it is only refreshed with a release note.
+const PUBLIC_SUFFIX_LIST: &str = include_str!("../assets/public_suffix_list.dat");
+
+/// Marks a security question keyword after the site, e.g. `bank.com?pet`.
+pub const QUESTION_MARKER: char = '?';
+
+/// Separates an account from the site, e.g. `alice@github.com`.
+pub const ACCOUNT_MARKER: char = '@';
+
+/// Separates a scheme such as `totp` from the name, e.g. `totp:github`.
+pub const SCHEME_MARKER: char = ':';
+
+/// A site as typed after a trigger prefix, split into its parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SiteInput<'a> {
+    pub site: &'a str,
+    /// Account on the site, if one was typed before the site.
+    pub account: Option<&'a str>,
+    /// Security question keyword; present means "derive an answer".
+    pub question: Option<&'a str>,
+    /// Set by `totp:<name>`: type the one-time code of the OTP entry `site`.
+    pub otp: bool,
+}
+
+impl<'a> SiteInput<'a> {
+    pub fn parse(input: &'a str) -> Self {
+        Self::parse_with(input, true)
+    }
+
+    /// Parses input as before accounts existed: `@` is part of the site name.
+    pub fn parse_without_account(input: &'a str) -> Self {
+        Self::parse_with(input, false)
+    }
+
+    fn parse_with(input: &'a str, accounts: bool) -> Self {
+        if let Some(name) = input.strip_prefix(OTP_PREFIX) {
+            return Self {
+                site: name,
+                account: None,
+                question: None,
+                otp: true,
+            };
+        }
+        let (rest, question) = match input.split_once(QUESTION_MARKER) {
+            Some((rest, question)) => (rest, Some(question).filter(|q| !q.is_empty())),
+            None => (input, None),
+        };
+        let (account, site) = match rest.rsplit_once(ACCOUNT_MARKER) {
+            Some((account, site)) if accounts && !account.is_empty() && !site.is_empty() => {
+                (Some(account), site)
+            }
+            _ => (None, rest),
+        };
+        Self {
+            site,
+            account,
+            question,
+            otp: false,
+        }
+    }
+}
+
+/// Reduces a URL or host to the site it belongs to, so that
+/// `https://www.github.com/login`, `www.github.com` and `github.com` are all
+/// `github.com`. Keeps only the registrable domain of [`site_host`]
+/// (`mail.google.co.uk` becomes `google.co.uk`). Input that is not a host name,
+/// such as an IP address or a bare label like `wifi`, is kept after cleanup.
+pub fn normalize_site(site: &str) -> String {
+    let host = site_host(site);
+    if host.parse::<IpAddr>().is_ok() {
+        return host;
+    }
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    match public_suffix_list().domain(host.as_bytes()) {
+        Some(domain) => String::from_utf8_lossy(domain.as_bytes()).into_owned(),
+        None => host.to_string(),
+    }
+}
+
+/// The host a URL or site points at, lowercased: drops the scheme, user info,
+/// path, query and port, and converts internationalized names to punycode.
+pub fn site_host(site: &str) -> String {
+    let site = site.trim().to_lowercase();
+    let rest = site
+        .split_once("://")
+        .map_or(site.as_str(), |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+    let host = host.trim_end_matches('.');
+    if host.is_empty() {
+        return site;
+    }
+    idna::domain_to_ascii(host).unwrap_or_else(|_| host.to_string())
+}
+
+fn public_suffix_list() -> &'static List {
+    static LIST: OnceLock<List> = OnceLock::new();
+    LIST.get_or_init(|| {
+        PUBLIC_SUFFIX_LIST
+            .parse()
+            .expect("embedded public suffix list is valid")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_question() {
+        let input = SiteInput::parse("bank.com?pet");
+        assert_eq!(input.site, "bank.com");
+        assert_eq!(input.question, Some("pet"));
+
+        let input = SiteInput::parse("github.com");
+        assert_eq!(input.site, "github.com");
+        assert_eq!(input.question, None);
+
+        assert_eq!(SiteInput::parse("bank.com?").question, None);
+    }
+
+    #[test]
+    fn test_parse_account() {
+        let input = SiteInput::parse("alice@github.com");
+        assert_eq!(input.site, "github.com");
+        assert_eq!(input.account, Some("alice"));
+
+        let input = SiteInput::parse("alice@bank.com?pet");
+        assert_eq!(input.site, "bank.com");
+        assert_eq!(input.account, Some("alice"));
+        assert_eq!(input.question, Some("pet"));
+
+        assert_eq!(SiteInput::parse("github.com").account, None);
+        assert_eq!(SiteInput::parse("@github.com").account, None);
+
+        let input = SiteInput::parse_without_account("work@github?pet");
+        assert_eq!(input.site, "work@github");
+        assert_eq!(input.account, None);
+        assert_eq!(input.question, Some("pet"));
+    }
+
+    #[test]
+    fn test_normalize_site() {
+        for site in [
+            "github.com",
+            "GitHub.com",
+            "www.github.com",
+            "https://www.github.com/login",
+            "http://user@github.com:8080/path?query#top",
+            "gist.github.com",
+            "github.com.",
+        ] {
+            assert_eq!(normalize_site(site), "github.com", "{}", site);
+        }
+        assert_eq!(normalize_site("mail.google.co.uk"), "google.co.uk");
+        assert_eq!(normalize_site("alice.github.io"), "alice.github.io");
+        assert_eq!(
+            normalize_site("https://shop.bücher.de/"),
+            "xn--bcher-kva.de"
+        );
+        assert_eq!(normalize_site("münchen.de"), "xn--mnchen-3ya.de");
+        assert_eq!(normalize_site("192.168.1.1:8443"), "192.168.1.1");
+        assert_eq!(normalize_site("wifi"), "wifi");
+        assert_eq!(normalize_site("localhost:3000"), "localhost");
+        assert_eq!(normalize_site("co.uk"), "co.uk");
+        assert_eq!(
+            site_host("https://DB-1.corp.example.com:5432/"),
+            "db-1.corp.example.com"
+        );
+    }
+
+    #[test]
+    fn test_parse_otp() {
+        let input = SiteInput::parse("totp:github");
+        assert!(input.otp);
+        assert_eq!(input.site, "github");
+        assert!(!SiteInput::parse("github.com").otp);
+    }
+}
@@ -0,0 +1,447 @@
+//! A minimal ssh-agent serving derived Ed25519 keys over a Unix socket.
+//!
+//! Keys are derived from the cached master key the first time a client asks
+//! for them and forgotten when the master key is cleared or changed. Only
+//! listing identities, signing, and the agent's own lock and unlock are
+//! supported; adding or removing keys is refused.
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use ed25519_dalek::Signer;
+use zeroize::Zeroizing;
+
+use super::{put_string, put_u32, SshKey, KEY_TYPE};
+use crate::core::generate_ssh_key;
+use crate::error::{EasyPasswordError, Result};
+use crate::master_key::MasterKeyCache;
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENT_SUCCESS: u8 = 6;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+const SSH_AGENTC_LOCK: u8 = 22;
+const SSH_AGENTC_UNLOCK: u8 = 23;
+
+/// Requests larger than this are not from a well-behaved client.
+const MAX_MESSAGE_LEN: usize = 256 * 1024;
+
+/// A key the agent offers: derived like `generate_ssh_key(label, counter)`.
+#[derive(Debug, Clone)]
+pub struct KeySpec {
+    pub label: String,
+    pub counter: u32,
+}
+
+#[derive(Clone)]
+pub struct SshAgent {
+    inner: Arc<AgentState>,
+}
+
+struct AgentState {
+    master_key: MasterKeyCache,
+    specs: Mutex<Vec<KeySpec>>,
+    /// Derived keys; `None` until first needed.
+    keys: Mutex<Option<Vec<SshKey>>>,
+    /// The passphrase from `ssh-add -x`, while locked.
+    lock: Mutex<Option<Zeroizing<Vec<u8>>>>,
+}
+
+impl SshAgent {
+    pub fn new(master_key: MasterKeyCache, specs: Vec<KeySpec>) -> Self {
+        Self {
+            inner: Arc::new(AgentState {
+                master_key,
+                specs: Mutex::new(specs),
+                keys: Mutex::new(None),
+                lock: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Replaces the keys on offer, e.g. after the config is reloaded.
+    pub fn set_keys(&self, specs: Vec<KeySpec>) {
+        *self.inner.specs.lock().unwrap() = specs;
+        self.forget_keys();
+    }
+
+    /// Drops the derived keys; they are derived again when next needed.
+    pub fn forget_keys(&self) {
+        *self.inner.keys.lock().unwrap() = None;
+    }
+
+    /// Listens on a Unix socket at `path`, serving each client on its own thread.
+    #[cfg(unix)]
+    pub fn serve(&self, path: &Path) -> Result<()> {
+        use std::fs;
+        use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+        use std::os::unix::net::UnixListener;
+
+        // A socket left behind by an earlier run would make bind fail.
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            // SAFETY: geteuid has no preconditions and cannot fail.
+            let uid = unsafe { libc::geteuid() };
+            if !metadata.file_type().is_socket() || metadata.uid() != uid {
+                return Err(EasyPasswordError::Config(format!(
+                    "{} exists and is not a socket owned by this user",
+                    path.display()
+                )));
+            }
+            fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(parent)?;
+        }
+        // Bind inside a private directory and move the socket into place only
+        // once it is owner-only, so no one else can connect in between.
+        let staging = path.with_file_name(format!(".easypassword-agent-{}", std::process::id()));
+        fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("agent.sock");
+        let bound = UnixListener::bind(&staged).and_then(|listener| {
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o600))?;
+            fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        // The socket is only still staged if a step failed.
+        let _ = fs::remove_file(&staged);
+        let _ = fs::remove_dir(&staging);
+        let listener = bound?;
+
+        let agent = self.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let agent = agent.clone();
+                        std::thread::spawn(move || {
+                            if let Err(e) = agent.serve_client(stream) {
+                                log::debug!("ssh-agent client error: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => log::error!("ssh-agent accept failed: {}", e),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn serve(&self, _path: &Path) -> Result<()> {
+        Err(EasyPasswordError::Config(
+            "The ssh-agent needs Unix domain sockets".to_string(),
+        ))
+    }
+
+    /// Answers length-prefixed requests until the client hangs up.
+    fn serve_client(&self, mut stream: impl Read + Write) -> io::Result<()> {
+        loop {
+            let mut len = [0u8; 4];
+            match stream.read_exact(&mut len) {
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                result => result?,
+            }
+            let len = u32::from_be_bytes(len) as usize;
+            if len > MAX_MESSAGE_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "ssh-agent request too large",
+                ));
+            }
+            let mut request = Zeroizing::new(vec![0u8; len]);
+            stream.read_exact(&mut request)?;
+
+            let response = self.handle_message(&request);
+            let mut framed = Vec::with_capacity(response.len() + 4);
+            put_string(&mut framed, &response);
+            stream.write_all(&framed)?;
+        }
+    }
+
+    /// Handles one request (message type and payload, without the length
+    /// prefix) and returns the response in the same form.
+    pub fn handle_message(&self, request: &[u8]) -> Vec<u8> {
+        let mut reader = Reader(request);
+        let response = match reader.byte() {
+            Some(SSH_AGENTC_REQUEST_IDENTITIES) => Some(self.identities()),
+            Some(SSH_AGENTC_SIGN_REQUEST) => self.sign(&mut reader),
+            Some(SSH_AGENTC_LOCK) => reader.string().and_then(|p| self.lock(p)),
+            Some(SSH_AGENTC_UNLOCK) => reader.string().and_then(|p| self.unlock(p)),
+            _ => None,
+        };
+        response.unwrap_or_else(|| vec![SSH_AGENT_FAILURE])
+    }
+
+    fn identities(&self) -> Vec<u8> {
+        self.with_keys(|keys| {
+            let mut response = vec![SSH_AGENT_IDENTITIES_ANSWER];
+            put_u32(&mut response, keys.len() as u32);
+            for key in keys {
+                put_string(&mut response, &key.public_key_blob());
+                put_string(&mut response, key.comment().as_bytes());
+            }
+            response
+        })
+    }
+
+    fn sign(&self, reader: &mut Reader) -> Option<Vec<u8>> {
+        let blob = reader.string()?;
+        let data = reader.string()?;
+        // Flags only select RSA hash algorithms, so they are ignored.
+        reader.u32()?;
+
+        self.with_keys(|keys| {
+            let key = keys.iter().find(|key| key.public_key_blob() == blob)?;
+            let signature = key.signing_key().sign(data);
+            let mut encoded = Vec::new();
+            put_string(&mut encoded, KEY_TYPE.as_bytes());
+            put_string(&mut encoded, &signature.to_bytes());
+
+            let mut response = vec![SSH_AGENT_SIGN_RESPONSE];
+            put_string(&mut response, &encoded);
+            log::info!("ssh-agent signed with key {}", key.comment());
+            Some(response)
+        })
+    }
+
+    fn lock(&self, passphrase: &[u8]) -> Option<Vec<u8>> {
+        let mut lock = self.inner.lock.lock().unwrap();
+        if lock.is_some() {
+            return None;
+        }
+        *lock = Some(Zeroizing::new(passphrase.to_vec()));
+        self.forget_keys();
+        Some(vec![SSH_AGENT_SUCCESS])
+    }
+
+    fn unlock(&self, passphrase: &[u8]) -> Option<Vec<u8>> {
+        let mut lock = self.inner.lock.lock().unwrap();
+        if lock.as_deref().map(Vec::as_slice) != Some(passphrase) {
+            return None;
+        }
+        *lock = None;
+        Some(vec![SSH_AGENT_SUCCESS])
+    }
+
+    /// Runs `f` with the keys on offer, deriving them if needed. There are
+    /// none while the agent is locked or no master key is set.
+    fn with_keys<T>(&self, f: impl FnOnce(&[SshKey]) -> T) -> T {
+        if self.inner.lock.lock().unwrap().is_some() {
+            return f(&[]);
+        }
+        let Some(master_key) = self.inner.master_key.get().map(Zeroizing::new) else {
+            self.forget_keys();
+            return f(&[]);
+        };
+
+        let mut keys = self.inner.keys.lock().unwrap();
+        let keys = keys.get_or_insert_with(|| {
+            let specs = self.inner.specs.lock().unwrap();
+            specs
+                .iter()
+                .filter_map(|spec| {
+                    generate_ssh_key(&master_key, &spec.label, spec.counter)
+                        .map_err(|e| {
+                            log::error!("ssh-agent failed to derive key {}: {}", spec.label, e)
+                        })
+                        .ok()
+                })
+                .collect()
+        });
+        f(keys)
+    }
+}
+
+/// Reads SSH wire-format fields, returning `None` on truncated input.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier};
+
+    fn agent() -> (SshAgent, MasterKeyCache) {
+        let spec = KeySpec {
+            label: "github".to_string(),
+            counter: 1,
+        };
+        let master_key = MasterKeyCache::new();
+        master_key.set("master".to_string());
+        (SshAgent::new(master_key.clone(), vec![spec]), master_key)
+    }
+
+    fn expected_key(agent: &SshAgent) -> SshKey {
+        let spec = agent.inner.specs.lock().unwrap()[0].clone();
+        generate_ssh_key("master", &spec.label, spec.counter).unwrap()
+    }
+
+    fn sign_request(blob: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, blob);
+        put_string(&mut request, data);
+        put_u32(&mut request, 0);
+        request
+    }
+
+    fn identity_count(agent: &SshAgent) -> u32 {
+        let response = agent.handle_message(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+        assert_eq!(response[0], SSH_AGENT_IDENTITIES_ANSWER);
+        Reader(&response[1..]).u32().unwrap()
+    }
+
+    #[test]
+    fn test_list_identities() {
+        let (agent, _) = agent();
+        let key = expected_key(&agent);
+        let response = agent.handle_message(&[SSH_AGENTC_REQUEST_IDENTITIES]);
+
+        let mut reader = Reader(&response);
+        assert_eq!(reader.byte(), Some(SSH_AGENT_IDENTITIES_ANSWER));
+        assert_eq!(reader.u32(), Some(1));
+        assert_eq!(reader.string(), Some(key.public_key_blob().as_slice()));
+        assert_eq!(reader.string(), Some(b"github".as_slice()));
+        assert!(reader.0.is_empty());
+    }
+
+    #[test]
+    fn test_sign_verifies() {
+        let (agent, _) = agent();
+        let key = expected_key(&agent);
+        let response = agent.handle_message(&sign_request(&key.public_key_blob(), b"data"));
+
+        let mut reader = Reader(&response);
+        assert_eq!(reader.byte(), Some(SSH_AGENT_SIGN_RESPONSE));
+        let mut signature = Reader(reader.string().unwrap());
+        assert_eq!(signature.string(), Some(KEY_TYPE.as_bytes()));
+        let signature = Signature::from_slice(signature.string().unwrap()).unwrap();
+        let verifying_key = key.signing_key().verifying_key();
+        assert!(verifying_key.verify(b"data", &signature).is_ok());
+
+        let unknown = agent.handle_message(&sign_request(b"unknown", b"data"));
+        assert_eq!(unknown, vec![SSH_AGENT_FAILURE]);
+    }
+
+    #[test]
+    fn test_cleared_master_key_hides_keys() {
+        let (agent, master_key) = agent();
+        let blob = expected_key(&agent).public_key_blob();
+        assert_eq!(identity_count(&agent), 1);
+
+        master_key.clear();
+        assert_eq!(identity_count(&agent), 0);
+        let response = agent.handle_message(&sign_request(&blob, b"data"));
+        assert_eq!(response, vec![SSH_AGENT_FAILURE]);
+    }
+
+    #[test]
+    fn test_lock_and_unlock() {
+        let (agent, _) = agent();
+        let mut lock = vec![SSH_AGENTC_LOCK];
+        put_string(&mut lock, b"secret");
+        assert_eq!(agent.handle_message(&lock), vec![SSH_AGENT_SUCCESS]);
+        assert_eq!(agent.handle_message(&lock), vec![SSH_AGENT_FAILURE]);
+        assert_eq!(identity_count(&agent), 0);
+
+        let mut wrong = vec![SSH_AGENTC_UNLOCK];
+        put_string(&mut wrong, b"wrong");
+        assert_eq!(agent.handle_message(&wrong), vec![SSH_AGENT_FAILURE]);
+
+        let mut unlock = vec![SSH_AGENTC_UNLOCK];
+        put_string(&mut unlock, b"secret");
+        assert_eq!(agent.handle_message(&unlock), vec![SSH_AGENT_SUCCESS]);
+        assert_eq!(identity_count(&agent), 1);
+    }
+
+    #[test]
+    fn test_framing_and_unsupported_requests() {
+        let (agent, _) = agent();
+        // An add-identity request, then a truncated sign request.
+        let mut input = Vec::new();
+        put_string(&mut input, &[17]);
+        put_string(&mut input, &[SSH_AGENTC_SIGN_REQUEST, 0, 0]);
+        let mut stream = io::Cursor::new(input);
+        let mut output = Vec::new();
+        agent
+            .serve_client(ReadWrite(&mut stream, &mut output))
+            .unwrap();
+        assert_eq!(
+            output,
+            [0, 0, 0, 1, SSH_AGENT_FAILURE, 0, 0, 0, 1, SSH_AGENT_FAILURE]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_socket_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("easypassword-agent-{}", std::process::id()));
+        let path = dir.join("nested").join("agent.sock");
+        let (agent, _) = agent();
+        agent.serve(&path).unwrap();
+        let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+
+        // A stale socket is replaced; anything else is left alone.
+        agent.serve(&path).unwrap();
+        let file = dir.join("not-a-socket");
+        std::fs::write(&file, "keep").unwrap();
+        assert!(agent.serve(&file).is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    struct ReadWrite<'a>(&'a mut io::Cursor<Vec<u8>>, &'a mut Vec<u8>);
+
+    impl Read for ReadWrite<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for ReadWrite<'_> {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
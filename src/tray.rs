@@ -73,7 +73,7 @@ pub fn run_tray(
                 tray_icon = Some(
                     TrayIconBuilder::new()
                         .with_menu(Box::new(tray_menu.clone()))
-                        .with_tooltip("EasyPassword")
+                        .with_tooltip(tooltip(None, false))
                         .with_icon(icon)
                         .build()
                         .expect("failed to create tray icon"),
@@ -122,6 +122,24 @@ pub fn run_tray(
                                 }
                             }
                         }
+                        TrayUpdate::MasterKey {
+                            fingerprint,
+                            rejected,
+                        } => {
+                            if let Some(tray_icon) = &tray_icon {
+                                let text = tooltip(fingerprint.as_deref(), rejected);
+                                if let Err(e) = tray_icon.set_tooltip(Some(text)) {
+                                    log::error!("failed to update tray tooltip: {}", e);
+                                }
+                            }
+                        }
+                        TrayUpdate::Status(status) => {
+                            if let Some(tray_icon) = &tray_icon {
+                                if let Err(e) = tray_icon.set_tooltip(Some(status)) {
+                                    log::error!("failed to update tray tooltip: {}", e);
+                                }
+                            }
+                        }
                     }
                 }
             }
@@ -130,6 +148,15 @@ pub fn run_tray(
     });
 }
 
+/// Shows the master key's fingerprint so a mistyped key is easy to spot.
+fn tooltip(fingerprint: Option<&str>, rejected: bool) -> String {
+    match (fingerprint, rejected) {
+        (Some(fingerprint), _) => format!("EasyPassword\nMaster key: {}", fingerprint),
+        (None, true) => "EasyPassword\nMaster key rejected: fingerprint mismatch".to_string(),
+        (None, false) => "EasyPassword\nMaster key not set".to_string(),
+    }
+}
+
 fn default_icon() -> tray_icon::Icon {
     tray_icon::Icon::from_rgba(generate_tray_icon_rgba_32(), 32, 32).expect("invalid tray icon")
 }